@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::{ActiveState, ServiceProperty, UnitState};
+
+/// Maximum number of concurrent systemctl invocations of batch operations
+pub(crate) const MAX_PARALLEL: usize = 8;
+
+/// Properties batch outcomes are told from, for `systemctl show --property`
+pub(crate) fn batch_properties() -> String {
+    [
+        ServiceProperty::Id,
+        ServiceProperty::LoadState,
+        ServiceProperty::ActiveState,
+        ServiceProperty::SubState,
+        ServiceProperty::InvocationID,
+    ]
+    .map(<&str>::from)
+    .join(",")
+}
+
+/// Returns `true` if `state` is a running one
+fn is_running(state: &UnitState) -> bool {
+    matches!(
+        state.active,
+        ActiveState::Active | ActiveState::Activating | ActiveState::Reloading
+    )
+}
+
+/// Per-unit outcome of a batch operation
+pub type BatchResult = BTreeMap<String, std::io::Result<()>>;
+
+/// Batch operations that systemctl applies to each unit,
+/// even when it fails for some of them
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Batch {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl Batch {
+    /// Returns the systemctl command
+    pub(crate) fn command(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+        }
+    }
+
+    /// Returns `true` if the unit properties are needed before the operation
+    /// to tell whether it succeeded
+    pub(crate) fn needs_before(&self) -> bool {
+        *self != Self::Stop
+    }
+
+    /// Returns whether the operation succeeded on a unit, from its properties
+    /// before and after systemctl returned, see [batch_properties].
+    /// A unit was (re)started only if it got a new invocation,
+    /// or was already running for `start`, since its state alone does not tell
+    /// a oneshot service that ran from a unit that was never started.
+    /// A unit was stopped if it is not running anymore, even if it is masked
+    /// or not found, as there was nothing to stop then.
+    pub(crate) fn succeeded(
+        &self,
+        before: &BTreeMap<String, String>,
+        after: &BTreeMap<String, String>,
+    ) -> bool {
+        let loaded = after.get("LoadState").map(String::as_str) == Some("loaded");
+        let state = UnitState::from_properties(after);
+        let invocation = |properties: &BTreeMap<String, String>| {
+            properties
+                .get("InvocationID")
+                .filter(|id| !id.is_empty())
+                .cloned()
+        };
+        let invoked = invocation(after).is_some() && invocation(after) != invocation(before);
+        match self {
+            Self::Start => {
+                loaded
+                    && state.active != ActiveState::Failed
+                    && (invoked
+                        || (is_running(&UnitState::from_properties(before)) && is_running(&state)))
+            },
+            Self::Restart => loaded && state.active != ActiveState::Failed && invoked,
+            Self::Stop => !is_running(&state),
+        }
+    }
+}
+
+/// Converts the exit `status` of `command` on `unit` into a result
+pub(crate) fn status_result(command: &str, unit: &str, status: ExitStatus) -> std::io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::other(format!(
+            "Failed to {command} \"{unit}\": {status}"
+        )))
+    }
+}
+
+/// Runs `operation` on each of `units`, at most [MAX_PARALLEL] at a time
+pub(crate) fn parallel<F>(units: &[&str], operation: F) -> BatchResult
+where
+    F: Fn(&str) -> std::io::Result<()> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(BatchResult::new());
+    std::thread::scope(|scope| {
+        for _ in 0..units.len().min(MAX_PARALLEL) {
+            scope.spawn(|| {
+                while let Some(unit) = units.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = operation(unit);
+                    if let Ok(mut results) = results.lock() {
+                        results.insert(unit.to_string(), result);
+                    }
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test::{fake_systemctl, FakeSystemCtl};
+
+    #[test]
+    fn test_parallel() {
+        let units: Vec<String> = (0..20).map(|i| format!("unit-{i}.service")).collect();
+        let units: Vec<&str> = units.iter().map(String::as_str).collect();
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let results = parallel(&units, |unit| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            if unit.ends_with("3.service") {
+                Err(Error::other("failed"))
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(results.len(), 20);
+        assert!(peak.load(Ordering::SeqCst) <= MAX_PARALLEL);
+        assert_eq!(results.values().filter(|result| result.is_err()).count(), 2);
+        assert!(results["unit-13.service"].is_err());
+    }
+
+    #[test]
+    fn test_succeeded() {
+        let properties = |load_state: &str, active: &str, invocation_id: &str| {
+            [
+                ("LoadState", load_state),
+                ("ActiveState", active),
+                ("InvocationID", invocation_id),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<String, String>>()
+        };
+        let never_started = properties("loaded", "inactive", "");
+        // oneshot service that ran
+        assert!(Batch::Start.succeeded(&never_started, &properties("loaded", "inactive", "a1")));
+        // not started at all, like when access was denied
+        assert!(!Batch::Start.succeeded(&never_started, &never_started));
+        let ran = properties("loaded", "inactive", "a1");
+        assert!(!Batch::Start.succeeded(&ran, &ran));
+        assert!(!Batch::Start.succeeded(&never_started, &properties("loaded", "failed", "a1")));
+        assert!(!Batch::Start.succeeded(&never_started, &properties("not-found", "inactive", "")));
+        // already running
+        let running = properties("loaded", "active", "a1");
+        assert!(Batch::Start.succeeded(&running, &running));
+        assert!(!Batch::Restart.succeeded(&running, &running));
+        assert!(Batch::Restart.succeeded(&running, &properties("loaded", "active", "b2")));
+        assert!(Batch::Stop.succeeded(&BTreeMap::new(), &properties("loaded", "failed", "a1")));
+        assert!(!Batch::Stop.succeeded(&BTreeMap::new(), &running));
+        // already stopped
+        assert!(Batch::Stop.succeeded(&BTreeMap::new(), &properties("masked", "inactive", "")));
+        assert!(Batch::Stop.succeeded(&BTreeMap::new(), &properties("not-found", "inactive", "")));
+        assert!(!Batch::Restart.succeeded(&never_started, &properties("masked", "active", "a1")));
+    }
+
+    #[test]
+    fn test_batch_operations() {
+        let mut fake = FakeSystemCtl::new("batch");
+        let counter = fake.file("count");
+        fake.write(&format!(
+            r#"case "$*" in
+    "start a.service b.service c.service") echo > {counter}; exit 1 ;;
+    restart*) exit 0 ;;
+    *show*)
+        # invocation IDs change once the start call ran
+        if [ -f {counter} ]; then invocation=a2; else invocation=a1; fi
+        printf 'Id=a.service\nLoadState=loaded\nActiveState=active\nSubState=running\nInvocationID=%s\n\n' $invocation
+        printf 'Id=b.service\nLoadState=loaded\nActiveState=failed\nSubState=failed\nInvocationID=b1\n\n'
+        printf 'Id=c.service\nLoadState=not-found\nActiveState=inactive\nSubState=dead\nInvocationID=\n' ;;
+    "enable a.service") exit 0 ;;
+    *) exit 1 ;;
+esac
+"#,
+            counter = counter.display()
+        ));
+        let ctl = fake.ctl();
+        let units = ["a.service", "b.service", "c.service"];
+        let results = ctl.start_many(&units);
+        assert!(results["a.service"].is_ok());
+        let e = results["b.service"].as_ref().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Failed to start \"b.service\": unit is loaded, failed (failed)"
+        );
+        assert!(results["c.service"].is_err());
+
+        let results = ctl.restart_many(&["a.service", "c.service"]);
+        assert!(results.values().all(Result::is_ok));
+
+        let results = ctl.enable_many(&["a.service", "b.service"]);
+        assert!(results["a.service"].is_ok());
+        assert!(results["b.service"].is_err());
+        assert!(ctl.stop_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_batch_stop() {
+        // systemctl refuses to stop units that are not loaded, which are not running
+        let fake = fake_systemctl(
+            "batch-stop",
+            r#"case "$*" in
+    *show*)
+        printf 'Id=masked.service\nLoadState=masked\nActiveState=inactive\nSubState=dead\nInvocationID=\n\n'
+        printf 'Id=gone.service\nLoadState=not-found\nActiveState=inactive\nSubState=dead\nInvocationID=\n\n'
+        printf 'Id=busy.service\nLoadState=loaded\nActiveState=active\nSubState=running\nInvocationID=c1\n' ;;
+    *) exit 5 ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        let results = ctl.stop_many(&["masked.service", "gone.service", "busy.service"]);
+        assert!(results["masked.service"].is_ok());
+        assert!(results["gone.service"].is_ok());
+        assert!(results["busy.service"].is_err());
+    }
+
+    #[test]
+    fn test_batch_access_denied() {
+        // the whole call is refused, units stay inactive
+        let fake = fake_systemctl(
+            "batch-denied",
+            r#"case "$*" in
+    *show*)
+        printf 'Id=a.service\nLoadState=loaded\nActiveState=inactive\nSubState=dead\nInvocationID=\n\n'
+        printf 'Id=b.service\nLoadState=loaded\nActiveState=inactive\nSubState=dead\nInvocationID=b1\n' ;;
+    *) exit 4 ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        for results in [
+            ctl.start_many(&["a.service", "b.service"]),
+            ctl.restart_many(&["a.service", "b.service"]),
+        ] {
+            assert_eq!(results.len(), 2);
+            assert!(results.values().all(Result::is_err));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::ErrorKind;
+use std::path::Path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// cgroup v2 mount point
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// systemd hierarchy mount point, on legacy cgroup v1 systems
+const CGROUP_V1_ROOT: &str = "/sys/fs/cgroup/systemd";
+
+/// Branch glyphs of a `systemctl status` control group tree, unicode and ASCII variants
+pub(crate) const BRANCH_GLYPHS: &[&str] = &["├─", "└─", "|-", "`-"];
+
+/// Control group of a unit: the processes it contains
+/// and its nested control groups
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ControlGroup {
+    /// Control group path, like `/system.slice/ssh.service`
+    pub path: String,
+    /// `(pid, command line)` of the processes directly attached to this group
+    pub processes: Vec<(u64, String)>,
+    /// Nested control groups
+    pub children: Vec<ControlGroup>,
+}
+
+/// Returns the command line of process `pid`, or its `[name]` for kernel threads.
+/// Returns `None` when the process no longer exists.
+fn cmdline(pid: u64) -> Option<String> {
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let args: Vec<_> = cmdline
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect();
+    if !args.is_empty() {
+        return Some(args.join(" "));
+    }
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(format!("[{}]", comm.trim_end()))
+}
+
+/// Parses a `├─702 /usr/sbin/sshd -D` process entry
+fn process_entry(entry: &str) -> Option<(u64, String)> {
+    let (pid, command) = entry.split_once(' ')?;
+    let pid = pid.parse().ok()?;
+    let command = command.trim();
+    // single argument command lines are quoted when they contain spaces
+    let command = command
+        .strip_prefix('"')
+        .and_then(|command| command.strip_suffix('"'))
+        .filter(|command| !command.contains('"'))
+        .unwrap_or(command);
+    Some((pid, command.to_string()))
+}
+
+impl ControlGroup {
+    /// Reads control group `path` and its processes from the local cgroup filesystem
+    pub fn read(path: &str) -> std::io::Result<Self> {
+        match Self::read_from(Path::new(CGROUP_ROOT), path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                Self::read_from(Path::new(CGROUP_V1_ROOT), path)
+            },
+            result => result,
+        }
+    }
+
+    /// Reads control group `path` within cgroup filesystem `root`
+    pub(crate) fn read_from(root: &Path, path: &str) -> std::io::Result<Self> {
+        let directory = root.join(path.trim_start_matches('/'));
+        let procs = std::fs::read_to_string(directory.join("cgroup.procs"))?;
+        let processes = procs
+            .lines()
+            .filter_map(|pid| pid.trim().parse().ok())
+            .filter_map(|pid| Some((pid, cmdline(pid)?)))
+            .collect();
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&directory)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        let mut children = Vec::new();
+        for name in names {
+            match Self::read_from(root, &format!("{}/{name}", path.trim_end_matches('/'))) {
+                Ok(child) => children.push(child),
+                // removed in the meantime
+                Err(e) if e.kind() == ErrorKind::NotFound => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Self {
+            path: path.to_string(),
+            processes,
+            children,
+        })
+    }
+
+    /// Builds a `ControlGroup` from the tree following `CGroup: $path`
+    /// in `systemctl status` output, like
+    /// ```text
+    /// ├─app.slice
+    /// │ └─1190 /usr/bin/dbus-daemon --session
+    /// └─init.scope
+    ///   └─1101 /lib/systemd/systemd --user
+    /// ```
+    pub(crate) fn from_status_tree(path: &str, lines: &[&str]) -> Self {
+        let indent = lines
+            .first()
+            .map(|line| line.len() - line.trim_start().len())
+            .unwrap_or_default();
+        let entries: Vec<(usize, &str)> = lines
+            .iter()
+            .filter_map(|line| {
+                let line = line.get(indent..)?;
+                let (branch, glyph) = BRANCH_GLYPHS
+                    .iter()
+                    .filter_map(|glyph| Some((line.find(glyph)?, glyph)))
+                    .min()?;
+                // every level is indented by a two characters wide "│ ", "| " or "  "
+                let depth = line[..branch].chars().count() / 2;
+                Some((depth, &line[branch + glyph.len()..]))
+            })
+            .collect();
+        Self::from_entries(path, &entries, 0)
+    }
+
+    /// Builds the group `path` from tree `entries` nested at `depth`
+    fn from_entries(path: &str, entries: &[(usize, &str)], depth: usize) -> Self {
+        let mut group = Self {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        for (i, (entry_depth, entry)) in entries.iter().enumerate() {
+            if *entry_depth != depth {
+                continue;
+            }
+            if let Some(process) = process_entry(entry) {
+                group.processes.push(process);
+            } else {
+                let end = entries[i + 1..]
+                    .iter()
+                    .position(|(d, _)| *d <= depth)
+                    .map_or(entries.len(), |end| i + 1 + end);
+                let child = format!("{}/{entry}", path.trim_end_matches('/'));
+                group
+                    .children
+                    .push(Self::from_entries(&child, &entries[i + 1..end], depth + 1));
+            }
+        }
+        group
+    }
+
+    /// Returns all processes of this group and its nested groups
+    pub fn all_processes(&self) -> Vec<&(u64, String)> {
+        let mut processes: Vec<_> = self.processes.iter().collect();
+        for child in self.children.iter() {
+            processes.extend(child.all_processes());
+        }
+        processes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_status_tree() {
+        let lines = [
+            "             ├─app.slice",
+            "             │ └─dbus.service",
+            "             │   └─1190 /usr/bin/dbus-daemon --session",
+            "             └─init.scope",
+            "               ├─1101 /lib/systemd/systemd --user",
+            "               └─1102 \"(sd-pam)\"",
+        ];
+        let group = ControlGroup::from_status_tree("/user.slice/user@1000.service", &lines);
+        assert!(group.processes.is_empty());
+        assert_eq!(group.children.len(), 2);
+        let app = &group.children[0];
+        assert_eq!(app.path, "/user.slice/user@1000.service/app.slice");
+        assert_eq!(
+            app.children[0].path,
+            "/user.slice/user@1000.service/app.slice/dbus.service"
+        );
+        assert_eq!(
+            app.children[0].processes,
+            vec![(1190, "/usr/bin/dbus-daemon --session".to_string())]
+        );
+        let init = &group.children[1];
+        assert_eq!(init.processes.len(), 2);
+        assert_eq!(init.processes[1], (1102, "(sd-pam)".to_string()));
+        assert_eq!(group.all_processes().len(), 3);
+    }
+
+    #[test]
+    fn test_read_from() {
+        let root = std::env::temp_dir().join(format!("systemctl-cgroup-{}", std::process::id()));
+        let service = root.join("system.slice/app.service");
+        std::fs::create_dir_all(service.join("worker")).unwrap();
+        std::fs::write(
+            service.join("cgroup.procs"),
+            format!("{}\n", std::process::id()),
+        )
+        .unwrap();
+        std::fs::write(service.join("worker/cgroup.procs"), "").unwrap();
+
+        let group = ControlGroup::read_from(&root, "/system.slice/app.service").unwrap();
+        assert_eq!(group.path, "/system.slice/app.service");
+        assert_eq!(group.processes.len(), 1);
+        assert_eq!(group.processes[0].0, std::process::id() as u64);
+        assert!(!group.processes[0].1.is_empty());
+        assert_eq!(group.children.len(), 1);
+        assert_eq!(group.children[0].path, "/system.slice/app.service/worker");
+        assert!(ControlGroup::read_from(&root, "/missing.service").is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+
+use bon::Builder;
+use strum_macros::{EnumString, IntoStaticStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Kind of requirement dependency between two units
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, EnumString, IntoStaticStr, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DependencyKind {
+    /// `Requires=`
+    Requires,
+    /// `Requisite=`
+    Requisite,
+    /// `Wants=`
+    Wants,
+    /// `BindsTo=`
+    BindsTo,
+    /// `PartOf=`
+    PartOf,
+    /// `Upholds=`
+    Upholds,
+}
+
+impl DependencyKind {
+    /// Returns the property a unit lists its dependencies of this kind in
+    pub(crate) fn property(&self) -> &'static str {
+        match self {
+            Self::PartOf => "ConsistsOf",
+            kind => kind.into(),
+        }
+    }
+
+    /// Returns the property a unit lists its dependents of this kind in
+    pub(crate) fn reverse_property(&self) -> &'static str {
+        match self {
+            Self::Requires => "RequiredBy",
+            Self::Requisite => "RequisiteOf",
+            Self::Wants => "WantedBy",
+            Self::BindsTo => "BoundBy",
+            Self::PartOf => "PartOf",
+            Self::Upholds => "UpheldBy",
+        }
+    }
+}
+
+/// Options of [crate::SystemCtl::list_dependencies_tree]
+#[derive(Builder, Clone, Debug, Default, PartialEq)]
+pub struct DependencyOptions {
+    /// Lists units depending on the unit instead (`--reverse`)
+    #[builder(default)]
+    pub reverse: bool,
+    /// Lists units ordered before the unit instead (`--before`)
+    #[builder(default)]
+    pub before: bool,
+    /// Lists units ordered after the unit instead (`--after`)
+    #[builder(default)]
+    pub after: bool,
+    /// Expands all units recursively, not only targets (`--all`)
+    #[builder(default)]
+    pub all: bool,
+    /// Only follows dependencies of these kinds, all kinds when empty.
+    /// Not applicable to `before` and `after` ordering listings.
+    #[builder(default)]
+    pub kinds: Vec<DependencyKind>,
+}
+
+impl DependencyOptions {
+    /// Returns the `list-dependencies` arguments matching these options
+    pub(crate) fn args(&self) -> std::io::Result<Vec<&'static str>> {
+        let directions = [self.reverse, self.before, self.after];
+        if directions.iter().filter(|direction| **direction).count() > 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "reverse, before and after are mutually exclusive",
+            ));
+        }
+        if (self.before || self.after) && !self.kinds.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "dependency kinds do not apply to ordering listings",
+            ));
+        }
+        let mut args = vec!["list-dependencies"];
+        for (enabled, arg) in [
+            (self.reverse, "--reverse"),
+            (self.before, "--before"),
+            (self.after, "--after"),
+            (self.all, "--all"),
+        ] {
+            if enabled {
+                args.push(arg);
+            }
+        }
+        Ok(args)
+    }
+
+    /// Returns the properties listing the followed dependencies of a unit
+    pub(crate) fn properties(&self) -> Vec<&'static str> {
+        self.kinds
+            .iter()
+            .map(|kind| {
+                if self.reverse {
+                    kind.reverse_property()
+                } else {
+                    kind.property()
+                }
+            })
+            .collect()
+    }
+}
+
+/// State glyphs prefixing dependencies, unicode and ASCII variants
+const STATE_GLYPHS: &[&str] = &["● ", "○ ", "× ", "* ", "o ", "x "];
+
+/// Tree glyphs indenting dependencies, unicode and ASCII variants
+const TREE_GLYPHS: &[&str] = &["  ", "│ ", "├─", "└─", "| ", "|-", "`-"];
+
+/// Unit of a dependency tree, as listed by `systemctl list-dependencies`
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DependencyNode {
+    /// Unit name
+    pub unit: String,
+    /// `true` if this unit is active
+    pub active: bool,
+    /// Dependencies of this unit
+    pub children: Vec<DependencyNode>,
+}
+
+impl DependencyNode {
+    /// Builds a tree from `systemctl list-dependencies` output,
+    /// with or without tree glyphs. `active` is only known
+    /// from the state glyphs, when present.
+    pub fn from_raw(raw: &str) -> std::io::Result<Self> {
+        // (indentation, node) of the current branch, from the root
+        let mut branch: Vec<(usize, Self)> = Vec::new();
+        for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+            let mut unit = line;
+            let mut active = false;
+            if !branch.is_empty() {
+                // state glyph, omitted in --plain mode
+                if let Some(rest) = STATE_GLYPHS.iter().find_map(|g| unit.strip_prefix(g)) {
+                    active = unit.starts_with(['●', '*']);
+                    unit = rest;
+                }
+                while let Some(rest) = TREE_GLYPHS.iter().find_map(|g| unit.strip_prefix(g)) {
+                    unit = rest;
+                }
+            }
+            let indent = line.chars().count() - unit.chars().count();
+            let node = Self {
+                unit: unit.trim().to_string(),
+                active,
+                children: Vec::new(),
+            };
+            if branch.is_empty() {
+                branch.push((indent, node));
+                continue;
+            }
+            while branch.len() > 1 && branch.last().is_some_and(|(i, _)| *i >= indent) {
+                Self::pop(&mut branch);
+            }
+            branch.push((indent, node));
+        }
+        while branch.len() > 1 {
+            Self::pop(&mut branch);
+        }
+        branch
+            .pop()
+            .map(|(_, root)| root)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "empty dependency listing"))
+    }
+
+    /// Moves the last node of `branch` into its parent
+    fn pop(branch: &mut Vec<(usize, Self)>) {
+        if let Some((_, node)) = branch.pop() {
+            if let Some((_, parent)) = branch.last_mut() {
+                parent.children.push(node);
+            }
+        }
+    }
+
+    /// Returns the names of all units of this tree, depth first
+    pub fn units(&self) -> Vec<&str> {
+        let mut units = vec![self.unit.as_str()];
+        for child in self.children.iter() {
+            units.extend(child.units());
+        }
+        units
+    }
+
+    /// Updates `active` and keeps the dependencies listed in one of `properties`
+    /// of their parent, from `systemctl show` properties of each unit
+    pub(crate) fn apply(
+        &mut self,
+        units: &BTreeMap<String, BTreeMap<String, String>>,
+        properties: &[&str],
+    ) {
+        if let Some(unit) = units.get(&self.unit) {
+            self.active = unit.get("ActiveState").map(String::as_str) == Some("active");
+        }
+        if let Some(unit) = units.get(&self.unit).filter(|_| !properties.is_empty()) {
+            let followed: Vec<&str> = properties
+                .iter()
+                .filter_map(|property| unit.get(*property))
+                .flat_map(|value| value.split_ascii_whitespace())
+                .collect();
+            self.children
+                .retain(|child| followed.contains(&child.unit.as_str()));
+        }
+        for child in self.children.iter_mut() {
+            child.apply(units, properties);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_raw() {
+        let plain = "multi-user.target
+  cron.service
+  basic.target
+    sockets.target
+      dbus.socket
+    sysinit.target
+  ssh.service
+";
+        let tree = DependencyNode::from_raw(plain).unwrap();
+        assert_eq!(tree.unit, "multi-user.target");
+        assert_eq!(tree.children.len(), 3);
+        assert_eq!(tree.children[1].unit, "basic.target");
+        assert_eq!(tree.children[1].children.len(), 2);
+        assert_eq!(tree.children[1].children[0].children[0].unit, "dbus.socket");
+        assert_eq!(tree.units().len(), 7);
+
+        let glyphs = "sound.target
+● ├─alsa-restore.service
+○ ├─alsa-state.service
+● └─sockets.target
+●   └─dbus.socket
+";
+        let tree = DependencyNode::from_raw(glyphs).unwrap();
+        assert_eq!(tree.children.len(), 3);
+        assert!(tree.children[0].active);
+        assert!(!tree.children[1].active);
+        assert_eq!(tree.children[2].children[0].unit, "dbus.socket");
+        let ascii = "local-fs.target
+* |--.mount
+o `-tmp.mount
+";
+        let tree = DependencyNode::from_raw(ascii).unwrap();
+        assert_eq!(
+            tree.units(),
+            vec!["local-fs.target", "-.mount", "tmp.mount"]
+        );
+        assert!(tree.children[0].active);
+        assert!(DependencyNode::from_raw("").is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let options = DependencyOptions::builder()
+            .reverse(true)
+            .kinds(vec![DependencyKind::Wants, DependencyKind::PartOf])
+            .build();
+        assert_eq!(
+            options.args().unwrap(),
+            vec!["list-dependencies", "--reverse"]
+        );
+        assert_eq!(options.properties(), vec!["WantedBy", "PartOf"]);
+        let options = DependencyOptions::builder()
+            .before(true)
+            .after(true)
+            .build();
+        assert!(options.args().is_err());
+        let options = DependencyOptions::builder()
+            .after(true)
+            .kinds(vec![DependencyKind::Requires])
+            .build();
+        assert!(options.args().is_err());
+    }
+}
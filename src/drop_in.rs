@@ -0,0 +1,171 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::UnitFile;
+
+/// A drop-in configuration file, `<unit>.d/<name>.conf`,
+/// overriding settings of a unit without editing it
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DropIn {
+    /// Name of the unit this drop-in applies to
+    pub unit: String,
+    /// Drop-in file name, including the `.conf` extension
+    pub name: String,
+    /// Path of the drop-in file
+    pub path: PathBuf,
+    /// Drop-in content
+    pub content: UnitFile,
+}
+
+/// Appends the `.service` suffix to unit names that lack a type,
+/// like systemctl itself does
+pub(crate) fn unit_name(unit: &str) -> String {
+    if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{unit}.service")
+    }
+}
+
+/// Appends the `.conf` extension when missing
+fn drop_in_name(name: &str) -> std::io::Result<String> {
+    if name.is_empty() || name.contains('/') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid drop-in name \"{name}\""),
+        ));
+    }
+    Ok(if name.ends_with(".conf") {
+        name.to_string()
+    } else {
+        format!("{name}.conf")
+    })
+}
+
+impl DropIn {
+    /// Returns the drop-in directory of `unit` within unit `directory`
+    pub(crate) fn directory(directory: &Path, unit: &str) -> PathBuf {
+        directory.join(format!("{}.d", unit_name(unit)))
+    }
+
+    /// Lists drop-ins of `unit` within unit `directory`, sorted by name
+    /// which is the order systemd applies them in
+    pub(crate) fn list(directory: &Path, unit: &str) -> std::io::Result<Vec<Self>> {
+        let directory = Self::directory(directory, unit);
+        let entries = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if name.ends_with(".conf") {
+                names.push(name);
+            }
+        }
+        names.sort();
+        let parent = directory.parent().unwrap_or(&directory);
+        names
+            .iter()
+            .map(|name| Self::read(parent, unit, name))
+            .collect()
+    }
+
+    /// Reads drop-in `name` of `unit` within unit `directory`
+    pub(crate) fn read(directory: &Path, unit: &str, name: &str) -> std::io::Result<Self> {
+        let name = drop_in_name(name)?;
+        let path = Self::directory(directory, unit).join(&name);
+        let content = std::fs::read_to_string(&path)?;
+        Ok(Self {
+            unit: unit_name(unit),
+            content: UnitFile::parse(path.to_str(), &content),
+            name,
+            path,
+        })
+    }
+
+    /// Creates or replaces drop-in `name` of `unit` within unit `directory`
+    pub(crate) fn write(
+        directory: &Path,
+        unit: &str,
+        name: &str,
+        content: &UnitFile,
+    ) -> std::io::Result<Self> {
+        let name = drop_in_name(name)?;
+        let drop_ins = Self::directory(directory, unit);
+        std::fs::create_dir_all(&drop_ins)?;
+        let path = drop_ins.join(&name);
+        // headers are only meaningful in `systemctl cat` output
+        let mut content = content.clone();
+        for fragment in content.fragments.iter_mut() {
+            fragment.path = None;
+        }
+        std::fs::write(&path, content.to_string())?;
+        Ok(Self {
+            unit: unit_name(unit),
+            content: UnitFile::parse(path.to_str(), &content.to_string()),
+            name,
+            path,
+        })
+    }
+
+    /// Deletes drop-in `name` of `unit` within unit `directory`,
+    /// and its drop-in directory once empty
+    pub(crate) fn remove(directory: &Path, unit: &str, name: &str) -> std::io::Result<()> {
+        let drop_ins = Self::directory(directory, unit);
+        std::fs::remove_file(drop_ins.join(drop_in_name(name)?))?;
+        if std::fs::read_dir(&drop_ins)?.next().is_none() {
+            std::fs::remove_dir(&drop_ins)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_drop_in_lifecycle() {
+        let directory =
+            std::env::temp_dir().join(format!("systemctl-drop-in-{}", std::process::id()));
+        assert!(DropIn::list(&directory, "app").unwrap().is_empty());
+
+        let mut content = UnitFile::default();
+        content.append("Service", "Restart", "always");
+        let drop_in = DropIn::write(&directory, "app", "override", &content).unwrap();
+        assert_eq!(drop_in.unit, "app.service");
+        assert_eq!(drop_in.name, "override.conf");
+        assert_eq!(drop_in.path, directory.join("app.service.d/override.conf"));
+
+        content.set("Service", "LimitNOFILE", "65536");
+        DropIn::write(&directory, "app.service", "10-limits.conf", &content).unwrap();
+
+        let drop_ins = DropIn::list(&directory, "app").unwrap();
+        let names: Vec<_> = drop_ins.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["10-limits.conf", "override.conf"]);
+        assert_eq!(
+            drop_ins[0].content.get("Service", "LimitNOFILE"),
+            Some("65536")
+        );
+
+        let drop_in = DropIn::read(&directory, "app", "override").unwrap();
+        assert_eq!(drop_in.content.get("Service", "Restart"), Some("always"));
+        assert_eq!(
+            drop_in.content.fragments[0].path.as_deref(),
+            drop_in.path.to_str()
+        );
+
+        assert!(DropIn::write(&directory, "app", "../escape", &content).is_err());
+
+        DropIn::remove(&directory, "app", "override").unwrap();
+        DropIn::remove(&directory, "app", "10-limits").unwrap();
+        assert!(!DropIn::directory(&directory, "app").exists());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
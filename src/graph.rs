@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error, ErrorKind};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::show::parse_properties;
+use crate::DependencyKind;
+
+/// Requirement dependencies the graph is built from
+const REQUIREMENTS: [DependencyKind; 4] = [
+    DependencyKind::Wants,
+    DependencyKind::Requires,
+    DependencyKind::BindsTo,
+    DependencyKind::PartOf,
+];
+
+/// Properties a [DependencyGraph] is built from
+pub(crate) const GRAPH_PROPERTIES: &str = "Id,Wants,Requires,BindsTo,PartOf,After,Before";
+
+/// Dependency graph of units, built from their
+/// `Wants`, `Requires`, `BindsTo`, `PartOf`, `After` and `Before` properties
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DependencyGraph {
+    /// All units of the graph, including the ones only referenced by other units
+    pub units: BTreeSet<String>,
+    /// `(unit, dependency, kind)` requirement dependencies,
+    /// like `("ssh.service", "network.target", Wants)`.
+    /// `PartOf` dependencies only propagate stops and restarts, they pull nothing in.
+    pub requirements: BTreeSet<(String, String, DependencyKind)>,
+    /// `(unit, predecessor)` ordering dependencies: `unit` starts after `predecessor`.
+    /// `Before=` dependencies are stored as the matching `After=` dependency.
+    pub ordering: BTreeSet<(String, String)>,
+}
+
+/// Double quotes a unit name for DOT
+fn quote(unit: &str) -> String {
+    format!("\"{}\"", unit.replace('"', "\\\""))
+}
+
+impl DependencyGraph {
+    /// Builds a graph from `systemctl show --property=Id,Wants,...` output of several units
+    pub fn from_show(output: &str) -> Self {
+        Self::from_properties(&parse_properties(output))
+    }
+
+    /// Builds the graph from the `systemctl show` properties of each unit
+    pub(crate) fn from_properties(units: &[BTreeMap<String, String>]) -> Self {
+        let mut graph = Self::default();
+        for properties in units {
+            graph.add_unit(properties);
+        }
+        graph
+    }
+
+    /// Adds the dependencies of a unit, from its `systemctl show` properties
+    fn add_unit(&mut self, properties: &BTreeMap<String, String>) {
+        let Some(unit) = properties.get("Id").filter(|id| !id.is_empty()) else {
+            return;
+        };
+        self.units.insert(unit.clone());
+        let values = |key: &str| {
+            properties
+                .get(key)
+                .map(|value| {
+                    value
+                        .split_ascii_whitespace()
+                        .map(str::to_string)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        for kind in REQUIREMENTS {
+            for dependency in values(kind.into()) {
+                self.units.insert(dependency.clone());
+                self.requirements.insert((unit.clone(), dependency, kind));
+            }
+        }
+        for predecessor in values("After") {
+            self.units.insert(predecessor.clone());
+            self.ordering.insert((unit.clone(), predecessor));
+        }
+        for successor in values("Before") {
+            self.units.insert(successor.clone());
+            self.ordering.insert((successor, unit.clone()));
+        }
+    }
+
+    /// Returns the units `unit` is ordered after
+    fn predecessors<'a>(&'a self, unit: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.ordering
+            .range((unit.to_string(), String::new())..)
+            .take_while(move |(u, _)| u == unit)
+            .map(|(_, predecessor)| predecessor.as_str())
+    }
+
+    /// Returns the units `unit` requires, wants or is bound to
+    fn dependencies<'a>(&'a self, unit: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.requirements
+            .iter()
+            .filter(move |(u, _, kind)| u == unit && *kind != DependencyKind::PartOf)
+            .map(|(_, dependency, _)| dependency.as_str())
+    }
+
+    /// Returns all units in start order, each unit after the units it is ordered after.
+    /// Fails with `ErrorKind::InvalidData` describing the cycle
+    /// if the ordering dependencies contain one, see [Self::find_cycle].
+    pub fn topological_order(&self) -> std::io::Result<Vec<String>> {
+        let mut remaining: BTreeMap<&str, usize> = self
+            .units
+            .iter()
+            .map(|unit| (unit.as_str(), self.predecessors(unit).count()))
+            .collect();
+        let mut successors: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (unit, predecessor) in self.ordering.iter() {
+            successors
+                .entry(predecessor.as_str())
+                .or_default()
+                .push(unit.as_str());
+        }
+        let mut ready: BTreeSet<&str> = remaining
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(unit, _)| *unit)
+            .collect();
+        let mut order = Vec::with_capacity(self.units.len());
+        while let Some(unit) = ready.pop_first() {
+            order.push(unit.to_string());
+            for successor in successors.get(unit).into_iter().flatten() {
+                if let Some(count) = remaining.get_mut(successor) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.insert(successor);
+                    }
+                }
+            }
+        }
+        if order.len() < self.units.len() {
+            let cycle = self.find_cycle().unwrap_or_default();
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Ordering cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+        Ok(order)
+    }
+
+    /// Returns an ordering cycle, if any, as the path of units
+    /// each ordered after the next one, ending with the first unit again
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        // units of the current path, and units known not to lead to a cycle
+        let mut path: Vec<&str> = Vec::new();
+        let mut done: BTreeSet<&str> = BTreeSet::new();
+        for unit in self.units.iter() {
+            if let Some(cycle) = self.visit(unit, &mut path, &mut done) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Depth first search of a cycle through `unit`, see [Self::find_cycle]
+    fn visit<'a>(
+        &'a self,
+        unit: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if done.contains(unit) {
+            return None;
+        }
+        if let Some(start) = path.iter().position(|u| *u == unit) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|u| u.to_string()).collect();
+            cycle.push(unit.to_string());
+            return Some(cycle);
+        }
+        path.push(unit);
+        for predecessor in self.predecessors(unit) {
+            if let Some(cycle) = self.visit(predecessor, path, done) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(unit);
+        None
+    }
+
+    /// Returns all units pulled in by `unit`, directly or not,
+    /// through `Wants`, `Requires` and `BindsTo` dependencies
+    pub fn reachable(&self, unit: &str) -> BTreeSet<String> {
+        let mut reachable = BTreeSet::new();
+        let mut pending = vec![unit];
+        while let Some(unit) = pending.pop() {
+            for dependency in self.dependencies(unit) {
+                if reachable.insert(dependency.to_string()) {
+                    pending.push(dependency);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Returns `true` if `unit` pulls in `dependency`, directly or not
+    pub fn is_reachable(&self, unit: &str, dependency: &str) -> bool {
+        self.reachable(unit).contains(dependency)
+    }
+
+    /// Exports this graph to Graphviz DOT.
+    /// Ordering dependencies are dashed, from units to their predecessors.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph units {\n");
+        for unit in self.units.iter() {
+            dot.push_str(&format!("    {};\n", quote(unit)));
+        }
+        for (unit, dependency, kind) in self.requirements.iter() {
+            let kind: &str = kind.into();
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{kind}\"];\n",
+                quote(unit),
+                quote(dependency)
+            ));
+        }
+        for (unit, predecessor) in self.ordering.iter() {
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"After\", style=dashed];\n",
+                quote(unit),
+                quote(predecessor)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Exports this graph to a Mermaid flowchart.
+    /// Ordering dependencies are dotted, from units to their predecessors.
+    pub fn to_mermaid(&self) -> String {
+        // unit names are not valid Mermaid identifiers
+        let ids: BTreeMap<&str, String> = self
+            .units
+            .iter()
+            .enumerate()
+            .map(|(i, unit)| (unit.as_str(), format!("u{i}")))
+            .collect();
+        let mut mermaid = String::from("flowchart LR\n");
+        for (unit, id) in ids.iter() {
+            mermaid.push_str(&format!("    {id}[\"{}\"]\n", unit.replace('"', "#quot;")));
+        }
+        for (unit, dependency, kind) in self.requirements.iter() {
+            let kind: &str = kind.into();
+            mermaid.push_str(&format!(
+                "    {} -->|{kind}| {}\n",
+                ids[unit.as_str()],
+                ids[dependency.as_str()]
+            ));
+        }
+        for (unit, predecessor) in self.ordering.iter() {
+            mermaid.push_str(&format!(
+                "    {} -.->|After| {}\n",
+                ids[unit.as_str()],
+                ids[predecessor.as_str()]
+            ));
+        }
+        mermaid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SHOW: &str = "Id=multi-user.target
+Wants=ssh.service cron.service
+Requires=basic.target
+After=basic.target
+Before=
+
+Id=ssh.service
+Wants=
+Requires=
+After=network.target basic.target
+Before=multi-user.target
+
+Id=cron.service
+PartOf=sysinit.target
+After=basic.target
+Before=multi-user.target
+
+Id=basic.target
+Requires=sysinit.target
+After=sysinit.target
+";
+
+    #[test]
+    fn test_graph() {
+        let graph = DependencyGraph::from_show(SHOW);
+        assert_eq!(graph.units.len(), 6);
+        assert!(graph.requirements.contains(&(
+            "multi-user.target".into(),
+            "ssh.service".into(),
+            DependencyKind::Wants
+        )));
+        assert!(graph
+            .ordering
+            .contains(&("multi-user.target".into(), "ssh.service".into())));
+
+        let order = graph.topological_order().unwrap();
+        let position = |unit: &str| order.iter().position(|u| u == unit).unwrap();
+        assert!(position("sysinit.target") < position("basic.target"));
+        assert!(position("network.target") < position("ssh.service"));
+        assert!(position("ssh.service") < position("multi-user.target"));
+        assert_eq!(graph.find_cycle(), None);
+
+        let reachable = graph.reachable("multi-user.target");
+        assert_eq!(
+            reachable.into_iter().collect::<Vec<_>>(),
+            vec![
+                "basic.target",
+                "cron.service",
+                "ssh.service",
+                "sysinit.target"
+            ]
+        );
+        assert!(graph.is_reachable("multi-user.target", "sysinit.target"));
+        assert!(!graph.is_reachable("ssh.service", "basic.target"));
+        // PartOf does not pull units in
+        assert!(graph.requirements.contains(&(
+            "cron.service".into(),
+            "sysinit.target".into(),
+            DependencyKind::PartOf
+        )));
+        assert!(!graph.is_reachable("cron.service", "sysinit.target"));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let graph = DependencyGraph::from_show(
+            "Id=a.service\nAfter=b.service\n\nId=b.service\nAfter=c.service\n\nId=c.service\nAfter=a.service\n\nId=d.service\nAfter=a.service\n",
+        );
+        assert_eq!(
+            graph.find_cycle(),
+            Some(vec![
+                "a.service".to_string(),
+                "b.service".to_string(),
+                "c.service".to_string(),
+                "a.service".to_string()
+            ])
+        );
+        let e = graph.topological_order().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            "Ordering cycle: a.service -> b.service -> c.service -> a.service"
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let graph =
+            DependencyGraph::from_show("Id=app.service\nRequires=db.service\nAfter=db.service\n");
+        assert_eq!(
+            graph.to_dot(),
+            "digraph units {
+    \"app.service\";
+    \"db.service\";
+    \"app.service\" -> \"db.service\" [label=\"Requires\"];
+    \"app.service\" -> \"db.service\" [label=\"After\", style=dashed];
+}
+"
+        );
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart LR
+    u0[\"app.service\"]
+    u1[\"db.service\"]
+    u0 -->|Requires| u1
+    u0 -.->|After| u1
+"
+        );
+    }
+}
@@ -0,0 +1,813 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Lines};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bon::Builder;
+use strum_macros::{EnumString, IntoStaticStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::DEFAULT_ENVIRONMENT;
+
+const JOURNALCTL_PATH: &str = "/usr/bin/journalctl";
+
+/// Log priority, from syslog
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, EnumString, IntoStaticStr, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Priority {
+    #[strum(serialize = "emerg")]
+    Emergency,
+    #[strum(serialize = "alert")]
+    Alert,
+    #[strum(serialize = "crit")]
+    Critical,
+    #[strum(serialize = "err")]
+    Error,
+    #[strum(serialize = "warning")]
+    Warning,
+    #[strum(serialize = "notice")]
+    Notice,
+    #[strum(serialize = "info")]
+    Info,
+    #[strum(serialize = "debug")]
+    Debug,
+}
+
+impl Priority {
+    /// Returns the priority of syslog `level`, 0 (emerg) to 7 (debug)
+    pub fn from_level(level: u8) -> Option<Self> {
+        Some(match level {
+            0 => Self::Emergency,
+            1 => Self::Alert,
+            2 => Self::Critical,
+            3 => Self::Error,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            7 => Self::Debug,
+            _ => return None,
+        })
+    }
+}
+
+/// Filters of a journal retrieval, see [Journal::entries]
+#[derive(Builder, Clone, Debug, Default, PartialEq)]
+pub struct JournalQuery {
+    /// Entries on or newer than this date, like `2024-10-14 09:00:00` or `-1h` (`--since`)
+    #[builder(into)]
+    pub since: Option<String>,
+    /// Entries on or older than this date (`--until`)
+    #[builder(into)]
+    pub until: Option<String>,
+    /// Entries of given boot, a boot ID or an offset like `0` for the current boot,
+    /// `-1` for the previous one (`--boot`)
+    #[builder(into)]
+    pub boot: Option<String>,
+    /// Entries of this priority or more important ones (`--priority`)
+    pub priority: Option<Priority>,
+    /// Only the last `lines` entries (`--lines`)
+    pub lines: Option<usize>,
+    /// Entries of given unit invocation, see `InvocationID`:
+    /// logged by it (`_SYSTEMD_INVOCATION_ID`) or by systemd about it (`INVOCATION_ID`)
+    #[builder(into)]
+    pub invocation_id: Option<String>,
+    /// Entries starting from this cursor, included (`--cursor`)
+    #[builder(into)]
+    pub cursor: Option<String>,
+    /// Entries following this cursor (`--after-cursor`)
+    #[builder(into)]
+    pub after_cursor: Option<String>,
+    /// File persisting the cursor of the last returned entry.
+    /// When it holds a cursor, it overrides `cursor` and `after_cursor`
+    /// and the retrieval resumes after that entry.
+    #[builder(into)]
+    pub cursor_file: Option<PathBuf>,
+}
+
+impl JournalQuery {
+    /// Returns the journalctl arguments matching these filters
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let options = [
+            ("--since", self.since.as_deref()),
+            ("--until", self.until.as_deref()),
+            ("--boot", self.boot.as_deref()),
+            ("--priority", self.priority.map(<&str>::from)),
+            ("--cursor", self.cursor.as_deref()),
+            ("--after-cursor", self.after_cursor.as_deref()),
+        ];
+        for (option, value) in options {
+            if let Some(value) = value {
+                args.push(format!("{option}={value}"));
+            }
+        }
+        if let Some(lines) = self.lines {
+            args.push(format!("--lines={lines}"));
+        }
+        if let Some(invocation_id) = &self.invocation_id {
+            // messages of systemd about the invocation, or logged by it
+            args.push(format!("INVOCATION_ID={invocation_id}"));
+            args.push("+".to_string());
+            args.push(format!("_SYSTEMD_INVOCATION_ID={invocation_id}"));
+        }
+        args
+    }
+
+    /// Returns the journalctl arguments matching these filters,
+    /// resuming after the cursor persisted in `cursor_file`, if any
+    pub(crate) fn resume_args(&self) -> std::io::Result<Vec<String>> {
+        let Some(path) = &self.cursor_file else {
+            return Ok(self.args());
+        };
+        let cursor = match std::fs::read_to_string(path) {
+            Ok(cursor) => cursor.trim().to_string(),
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        if cursor.is_empty() {
+            return Ok(self.args());
+        }
+        let query = Self {
+            cursor: None,
+            after_cursor: Some(cursor),
+            ..self.clone()
+        };
+        Ok(query.args())
+    }
+}
+
+/// Journal entry, from `journalctl -o json`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct JournalEntry {
+    /// Entry position, to resume reading from (`__CURSOR`)
+    pub cursor: String,
+    /// Time the entry was received at (`__REALTIME_TIMESTAMP`)
+    pub timestamp: SystemTime,
+    /// Boot the entry was logged during (`_BOOT_ID`)
+    pub boot_id: Option<String>,
+    /// Unit the entry is about (`UNIT`, `USER_UNIT`), for entries logged by systemd,
+    /// otherwise the unit that logged it (`_SYSTEMD_UNIT`, `_SYSTEMD_USER_UNIT`)
+    pub unit: Option<String>,
+    /// Unit invocation the entry is about (`INVOCATION_ID`),
+    /// otherwise the unit invocation that logged it (`_SYSTEMD_INVOCATION_ID`)
+    pub invocation_id: Option<String>,
+    /// Entry priority (`PRIORITY`)
+    pub priority: Option<Priority>,
+    /// Process that logged the entry (`_PID`)
+    pub pid: Option<u64>,
+    /// Syslog identifier, usually the program name (`SYSLOG_IDENTIFIER`)
+    pub identifier: Option<String>,
+    /// Log message (`MESSAGE`)
+    pub message: String,
+    /// All fields of the entry. Binary values are converted lossily,
+    /// repeated fields are joined with line breaks.
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Converts a JSON field value: a string, a byte array for binary data,
+/// or an array of those for repeated fields. Large fields are `null`.
+fn field_value(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Array(items) if items.iter().all(|item| item.is_u64()) => {
+            let bytes: Vec<u8> = items
+                .iter()
+                .filter_map(|item| item.as_u64())
+                .map(|byte| byte as u8)
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).to_string())
+        },
+        serde_json::Value::Array(items) => {
+            let values: Vec<String> = items.iter().filter_map(field_value).collect();
+            Some(values.join("\n"))
+        },
+        _ => None,
+    }
+}
+
+impl JournalEntry {
+    /// Parses a line of `journalctl -o json` output
+    pub fn from_json(line: &str) -> std::io::Result<Self> {
+        let object = match serde_json::from_str(line) {
+            Ok(serde_json::Value::Object(object)) => object,
+            Ok(_) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Journal entry is not an object",
+                ))
+            },
+            Err(e) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid journal entry: {e}"),
+                ))
+            },
+        };
+        let fields: BTreeMap<String, String> = object
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), field_value(value)?)))
+            .collect();
+        let field = |key: &str| fields.get(key).cloned();
+        let cursor = field("__CURSOR")
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Journal entry has no cursor"))?;
+        let timestamp = field("__REALTIME_TIMESTAMP")
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .map(|usec| UNIX_EPOCH + Duration::from_micros(usec))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Journal entry has no timestamp"))?;
+        Ok(Self {
+            cursor,
+            timestamp,
+            boot_id: field("_BOOT_ID"),
+            unit: field("UNIT")
+                .or_else(|| field("USER_UNIT"))
+                .or_else(|| field("_SYSTEMD_UNIT"))
+                .or_else(|| field("_SYSTEMD_USER_UNIT")),
+            invocation_id: field("INVOCATION_ID").or_else(|| field("_SYSTEMD_INVOCATION_ID")),
+            priority: field("PRIORITY")
+                .and_then(|level| level.parse().ok())
+                .and_then(Priority::from_level),
+            pid: field("_PID").and_then(|pid| pid.parse().ok()),
+            identifier: field("SYSLOG_IDENTIFIER"),
+            message: field("MESSAGE").unwrap_or_default(),
+            fields,
+        })
+    }
+}
+
+/// Struct with API calls to journalctl, a companion of [crate::SystemCtl]
+/// to retrieve the logs of units.
+///
+/// Use the `::default()` impl for the system journal,
+/// or [crate::SystemCtl::journal] for the journal matching a `SystemCtl`.
+#[derive(Builder, Default, Clone, Debug)]
+pub struct Journal {
+    /// The path to the journalctl binary, by default it's [JOURNALCTL_PATH]
+    path: Option<String>,
+    /// Allows passing global arguments to journalctl
+    #[builder(default)]
+    additional_args: Vec<String>,
+    /// Reads the journal of the calling user (`--user`),
+    /// and matches units as user units (`--user-unit`)
+    #[builder(default)]
+    user: bool,
+    /// Reads the journal of a local container (`--machine`)
+    machine: Option<String>,
+    /// Reads the journal files of the given root directory (`--root`)
+    root: Option<String>,
+    /// Reads the journal files of the given disk image (`--image`)
+    image: Option<String>,
+    /// Environment variables to run journalctl with.
+    /// They override the defaults: `LC_ALL=C`, `SYSTEMD_COLORS=0` and `SYSTEMD_PAGER=`.
+    #[builder(default)]
+    environment: HashMap<String, String>,
+}
+
+impl Journal {
+    /// Invokes `journalctl -o json $args` for given `unit`
+    pub(crate) fn spawn_child(&self, unit: &str, args: &[String]) -> std::io::Result<Child> {
+        let mut global_args = Vec::new();
+        if self.user {
+            global_args.push("--user".to_string());
+        }
+        if let Some(machine) = &self.machine {
+            global_args.push(format!("--machine={machine}"));
+        }
+        if let Some(root) = &self.root {
+            global_args.push(format!("--root={root}"));
+        }
+        if let Some(image) = &self.image {
+            global_args.push(format!("--image={image}"));
+        }
+        let unit_option = if self.user { "--user-unit" } else { "--unit" };
+        std::process::Command::new(self.path.as_deref().unwrap_or(JOURNALCTL_PATH))
+            .args(&self.additional_args)
+            .args(global_args)
+            .args(["--no-pager", "--output=json"])
+            .arg(format!("{unit_option}={unit}"))
+            .args(args)
+            .envs(DEFAULT_ENVIRONMENT.iter().copied())
+            .envs(&self.environment)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+    }
+
+    /// Returns the journal entries of given `unit` matching `query`, oldest first
+    pub fn entries(&self, unit: &str, query: &JournalQuery) -> std::io::Result<Vec<JournalEntry>> {
+        let output = self
+            .spawn_child(unit, &query.resume_args()?)?
+            .wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::other(format!(
+                "journalctl failed with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid utf8 data in stdout"))?;
+        let entries: Vec<JournalEntry> = stdout
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(JournalEntry::from_json)
+            .collect::<std::io::Result<_>>()?;
+        if let (Some(path), Some(entry)) = (&query.cursor_file, entries.last()) {
+            save_cursor(path, &entry.cursor)?;
+        }
+        Ok(entries)
+    }
+
+    /// Follows the journal of given `unit`, like `journalctl --follow`,
+    /// returning entries as they get logged.
+    ///
+    /// Unless resuming from a cursor, journalctl first returns the last 10 entries:
+    /// use `lines(0)` to only get new ones.
+    /// With a `cursor_file`, the cursor of each returned entry is persisted,
+    /// so that a restarted consumer resumes where it left off.
+    pub fn follow(&self, unit: &str, query: &JournalQuery) -> std::io::Result<JournalFollower> {
+        let mut args = vec!["--follow".to_string()];
+        args.extend(query.resume_args()?);
+        let mut child = self.spawn_child(unit, &args)?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| Error::other("journalctl stdout is not piped"))?;
+        Ok(JournalFollower {
+            child: ChildGuard::new(child),
+            lines: BufReader::new(stdout).lines(),
+            cursor_file: query.cursor_file.clone().map(|path| CursorFile {
+                path,
+                pending: None,
+            }),
+            done: false,
+        })
+    }
+}
+
+/// Persists `cursor` into `path`, atomically
+fn save_cursor(path: &Path, cursor: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, cursor)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Cursor file of a [JournalFollower]. The cursor of an entry is only persisted
+/// once the next one is requested, or the follower is dropped,
+/// so that an entry being processed when the consumer died is delivered again.
+#[derive(Debug)]
+struct CursorFile {
+    path: PathBuf,
+    /// Cursor of the last returned entry, not persisted yet
+    pending: Option<String>,
+}
+
+impl CursorFile {
+    /// Persists the pending cursor
+    fn commit(&mut self) -> std::io::Result<()> {
+        match self.pending.take() {
+            Some(cursor) => save_cursor(&self.path, &cursor),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for CursorFile {
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
+}
+
+/// Number of journalctl stderr lines kept to report its failure
+const STDERR_LINES: usize = 10;
+
+/// journalctl process, killed once dropped.
+/// Its stderr is drained on a thread meanwhile, so that journalctl
+/// never blocks on a full pipe.
+#[derive(Debug)]
+struct ChildGuard {
+    child: Child,
+    /// Returns the last stderr lines, once journalctl closed it
+    stderr: Option<JoinHandle<String>>,
+}
+
+impl ChildGuard {
+    fn new(mut child: Child) -> Self {
+        let stderr = child.stderr.take().map(|pipe| {
+            std::thread::spawn(move || {
+                let mut tail = VecDeque::with_capacity(STDERR_LINES);
+                for line in BufReader::new(pipe).split(b'\n').map_while(Result::ok) {
+                    if tail.len() == STDERR_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(String::from_utf8_lossy(&line).to_string());
+                }
+                Vec::from(tail).join("\n")
+            })
+        });
+        Self { child, stderr }
+    }
+
+    /// Waits for journalctl to exit once its output ended,
+    /// returns an error if it failed
+    fn exit_error(&mut self) -> Option<Error> {
+        let status = self.child.wait();
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|stderr| stderr.join().ok())
+            .unwrap_or_default();
+        match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(Error::other(format!(
+                "journalctl failed with {status}: {}",
+                stderr.trim()
+            ))),
+            Err(e) => Some(e),
+        }
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads journal entries from the output of journalctl
+fn read_entry(lines: &mut Lines<BufReader<ChildStdout>>) -> Option<std::io::Result<JournalEntry>> {
+    for line in lines {
+        match line {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => return Some(JournalEntry::from_json(&line)),
+            Err(e) => return Some(Err(e)),
+        }
+    }
+    None
+}
+
+/// Live journal entries of a unit, see [Journal::follow].
+///
+/// It blocks until the next entry is logged, and ends only if journalctl exits.
+/// journalctl is killed once the follower is dropped.
+#[derive(Debug)]
+pub struct JournalFollower {
+    child: ChildGuard,
+    lines: Lines<BufReader<ChildStdout>>,
+    cursor_file: Option<CursorFile>,
+    /// Set once journalctl exited
+    done: bool,
+}
+
+impl JournalFollower {
+    /// Converts this follower into an async `Stream` of entries.
+    /// Reading happens on a background thread, stopped once the stream is dropped.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> stream::JournalStream {
+        stream::JournalStream::new(self)
+    }
+}
+
+impl Iterator for JournalFollower {
+    type Item = std::io::Result<JournalEntry>;
+    /// Blocks until the next entry
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(Err(e)) = self.cursor_file.as_mut().map(CursorFile::commit) {
+            return Some(Err(e));
+        }
+        if self.done {
+            return None;
+        }
+        match read_entry(&mut self.lines) {
+            Some(Ok(entry)) => {
+                if let Some(cursor_file) = &mut self.cursor_file {
+                    cursor_file.pending = Some(entry.cursor.clone());
+                }
+                Some(Ok(entry))
+            },
+            Some(Err(e)) => Some(Err(e)),
+            None => {
+                self.done = true;
+                self.child.exit_error().map(Err)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+mod stream {
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    use futures_core::Stream;
+
+    use super::{read_entry, ChildGuard, CursorFile, JournalEntry, JournalFollower};
+
+    /// State shared between a [JournalStream] and its reading thread
+    #[derive(Default)]
+    struct Shared {
+        /// Entries not yet returned by the stream
+        entries: VecDeque<std::io::Result<JournalEntry>>,
+        /// Waker of the task awaiting the next entry
+        waker: Option<Waker>,
+        /// Set once journalctl output ended
+        ended: bool,
+        /// Set once the stream is dropped
+        closed: bool,
+    }
+
+    /// Async `Stream` of [JournalEntry]s, see [JournalFollower::into_stream]
+    pub struct JournalStream {
+        shared: Arc<Mutex<Shared>>,
+        cursor_file: Option<CursorFile>,
+        /// Set once journalctl exited
+        done: bool,
+        // dropped last, unblocking the reading thread
+        child: ChildGuard,
+    }
+
+    impl JournalStream {
+        pub(super) fn new(follower: JournalFollower) -> Self {
+            let JournalFollower {
+                child,
+                mut lines,
+                cursor_file,
+                done,
+            } = follower;
+            let shared = Arc::new(Mutex::new(Shared {
+                ended: done,
+                ..Default::default()
+            }));
+            let thread_shared = shared.clone();
+            std::thread::spawn(move || loop {
+                let entry = read_entry(&mut lines);
+                let Ok(mut shared) = thread_shared.lock() else {
+                    return;
+                };
+                if shared.closed {
+                    return;
+                }
+                let ended = entry.is_none();
+                match entry {
+                    Some(entry) => shared.entries.push_back(entry),
+                    None => shared.ended = true,
+                }
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+                if ended {
+                    return;
+                }
+            });
+            Self {
+                shared,
+                cursor_file,
+                done,
+                child,
+            }
+        }
+    }
+
+    impl Stream for JournalStream {
+        type Item = std::io::Result<JournalEntry>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = &mut *self;
+            if let Some(Err(e)) = this.cursor_file.as_mut().map(CursorFile::commit) {
+                return Poll::Ready(Some(Err(e)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut shared = this.shared.lock().unwrap_or_else(|e| e.into_inner());
+            match shared.entries.pop_front() {
+                Some(Ok(entry)) => {
+                    if let Some(cursor_file) = &mut this.cursor_file {
+                        cursor_file.pending = Some(entry.cursor.clone());
+                    }
+                    Poll::Ready(Some(Ok(entry)))
+                },
+                Some(Err(e)) => Poll::Ready(Some(Err(e))),
+                None if shared.ended => {
+                    drop(shared);
+                    this.done = true;
+                    Poll::Ready(this.child.exit_error().map(Err))
+                },
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                },
+            }
+        }
+    }
+
+    impl Drop for JournalStream {
+        fn drop(&mut self) {
+            if let Ok(mut shared) = self.shared.lock() {
+                shared.closed = true;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use stream::JournalStream;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_entry_from_json() {
+        let entries: Vec<JournalEntry> = include_str!("../fixtures/journal/ssh.json")
+            .lines()
+            .map(|line| JournalEntry::from_json(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 3);
+        let entry = &entries[0];
+        assert_eq!(entry.unit.as_deref(), Some("ssh.service"));
+        assert_eq!(entry.pid, Some(702));
+        assert_eq!(entry.priority, Some(Priority::Info));
+        assert_eq!(entry.identifier.as_deref(), Some("sshd"));
+        assert_eq!(entry.message, "Server listening on 0.0.0.0 port 22.");
+        assert_eq!(
+            entry.timestamp,
+            UNIX_EPOCH + Duration::from_micros(1728897121345678)
+        );
+        assert_eq!(
+            entry.invocation_id.as_deref(),
+            Some("9f1c2b7d0e3a4c5b8d7e6f5a4b3c2d1e")
+        );
+        // binary message
+        assert_eq!(entries[1].message, "bad\u{fffd}input");
+        assert_eq!(entries[1].priority, Some(Priority::Error));
+        // repeated field and unit logged about by systemd
+        assert_eq!(entries[2].unit.as_deref(), Some("ssh.service"));
+        assert_eq!(entries[2].invocation_id, entry.invocation_id);
+        assert!(!entries[1].fields.contains_key("_AUDIT_SESSION"));
+        assert_eq!(
+            entries[2].fields.get("TAG").map(String::as_str),
+            Some("a\nb")
+        );
+        assert!(JournalEntry::from_json("{}").is_err());
+        assert!(JournalEntry::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_query_args() {
+        let query = JournalQuery::builder()
+            .since("-1h")
+            .boot("0")
+            .priority(Priority::Warning)
+            .lines(50)
+            .invocation_id("9f1c")
+            .build();
+        assert_eq!(
+            query.args(),
+            vec![
+                "--since=-1h",
+                "--boot=0",
+                "--priority=warning",
+                "--lines=50",
+                "INVOCATION_ID=9f1c",
+                "+",
+                "_SYSTEMD_INVOCATION_ID=9f1c"
+            ]
+        );
+        assert!(Priority::Error < Priority::Warning);
+        assert_eq!(Priority::from_level(8), None);
+    }
+
+    /// Returns a [Journal] invoking a fake journalctl, that records its arguments
+    /// and prints the `ssh.json` fixture, then follows forever if `--follow` is set
+    fn fake_journal(name: &str) -> (Journal, PathBuf, PathBuf) {
+        let dir = std::env::temp_dir();
+        let args = dir.join(format!("journalctl-{name}-{}.args", std::process::id()));
+        let script = dir.join(format!("journalctl-{name}-{}.sh", std::process::id()));
+        std::fs::write(
+            &script,
+            format!(
+                r#"printf '%s\n' "$@" > {args}
+cat {fixture}
+case "$*" in
+    *--follow*) exec sleep 60 ;;
+esac
+"#,
+                args = args.display(),
+                fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/journal/ssh.json"),
+            ),
+        )
+        .unwrap();
+        let journal = Journal::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![script.to_string_lossy().to_string()])
+            .build();
+        (journal, script, args)
+    }
+
+    #[test]
+    fn test_follow() {
+        let (journal, script, args) = fake_journal("follow");
+        let cursor_file =
+            std::env::temp_dir().join(format!("journalctl-follow-{}.cursor", std::process::id()));
+        let query = JournalQuery::builder()
+            .lines(0)
+            .cursor_file(cursor_file.clone())
+            .build();
+
+        let mut follower = journal.follow("ssh.service", &query).unwrap();
+        let first = follower.next().unwrap().unwrap();
+        assert_eq!(first.pid, Some(702));
+        // only persisted once the next entry is requested
+        assert!(!cursor_file.exists());
+        let second = follower.next().unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&cursor_file).unwrap(), first.cursor);
+        drop(follower);
+        assert_eq!(
+            std::fs::read_to_string(&cursor_file).unwrap(),
+            second.cursor
+        );
+
+        let mut follower = journal.follow("ssh.service", &query).unwrap();
+        follower.next().unwrap().unwrap();
+        let recorded = std::fs::read_to_string(&args).unwrap();
+        let recorded: Vec<&str> = recorded.lines().collect();
+        assert!(recorded.contains(&"--follow"));
+        assert!(recorded.contains(&"--unit=ssh.service"));
+        let after_cursor = format!("--after-cursor={}", second.cursor);
+        assert!(recorded.contains(&after_cursor.as_str()));
+        drop(follower);
+
+        let entries = journal
+            .entries("ssh.service", &JournalQuery::default())
+            .unwrap();
+        assert_eq!(entries.len(), 3);
+
+        std::fs::remove_file(script).unwrap();
+        std::fs::remove_file(args).unwrap();
+        std::fs::remove_file(cursor_file).unwrap();
+    }
+
+    #[test]
+    fn test_follow_chatty_stderr() {
+        let dir = std::env::temp_dir();
+        let script = dir.join(format!("journalctl-chatty-{}.sh", std::process::id()));
+        // more than a pipe buffer of warnings, before any entry
+        std::fs::write(
+            &script,
+            format!(
+                r#"seq -f 'warning %g' 50000 >&2
+echo 'Failed to watch journal' >&2
+cat {fixture}
+exit 1
+"#,
+                fixture = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/journal/ssh.json"),
+            ),
+        )
+        .unwrap();
+        let journal = Journal::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![script.to_string_lossy().to_string()])
+            .build();
+        let results: Vec<_> = journal
+            .follow("ssh.service", &JournalQuery::default())
+            .unwrap()
+            .collect();
+        std::fs::remove_file(script).unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(results[..3].iter().all(Result::is_ok));
+        let e = results[3].as_ref().unwrap_err().to_string();
+        assert!(e.ends_with("warning 50000\nFailed to watch journal"), "{e}");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_follow_stream() {
+        use futures_core::Stream;
+        let (journal, script, args) = fake_journal("follow-stream");
+        let mut stream = journal
+            .follow("ssh.service", &JournalQuery::default())
+            .unwrap()
+            .into_stream();
+        for pid in [702, 1201, 1] {
+            let entry = futures_executor::block_on(std::future::poll_fn(|cx| {
+                std::pin::Pin::new(&mut stream).poll_next(cx)
+            }))
+            .unwrap()
+            .unwrap();
+            assert_eq!(entry.pid, Some(pid));
+        }
+        drop(stream);
+        std::fs::remove_file(script).unwrap();
+        std::fs::remove_file(args).unwrap();
+    }
+}
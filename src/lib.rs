@@ -1,32 +1,148 @@
 //! Crate to manage and monitor services through `systemctl`
 //! Homepage: <https://github.com/gwbres/systemctl>
 #![doc=include_str!("../README.md")]
+use std::collections::{BTreeMap, HashMap};
 use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::process::{Child, ExitStatus};
 use std::str::FromStr;
-use strum_macros::EnumString;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use strum_macros::{EnumString, IntoStaticStr};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 const SYSTEMCTL_PATH: &str = "/usr/bin/systemctl";
 
+/// Environment systemctl always runs with, so its output
+/// does not depend on the host locale or terminal
+const DEFAULT_ENVIRONMENT: &[(&str, &str)] = &[
+    ("LC_ALL", "C"),
+    ("SYSTEMD_COLORS", "0"),
+    ("SYSTEMD_PAGER", ""),
+];
+
+/// Arguments of commands whose output is parsed: no pager, no tree glyphs,
+/// no ellipsized columns and no header or footer
+const CAPTURE_ARGS: &[&str] = &["--no-pager", "--plain", "--full", "--no-legend"];
+
+/// systemctl commands that remain valid when operating
+/// on a root directory or image with `--root` / `--image`
+const OFFLINE_COMMANDS: &[&str] = &[
+    "enable",
+    "disable",
+    "reenable",
+    "mask",
+    "unmask",
+    "preset",
+    "preset-all",
+    "is-enabled",
+    "list-unit-files",
+    "cat",
+    "get-default",
+    "set-default",
+];
+
+/// Maximum number of units retrieved by a single `systemctl show` call
+const SHOW_CHUNK: usize = 256;
+
+/// systemctl commands refused when operating on a remote host or container
+const LOCAL_COMMANDS: &[&str] = &["cat", "edit"];
+
+/// Error of units that do not exist
+fn not_found(unit: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("Unit or service \"{unit}\" does not exist"),
+    )
+}
+
 use bon::Builder;
 
 mod service_property;
 pub use service_property::ServiceProperty;
 
+mod unit_file;
+pub use unit_file::{UnitFile, UnitFileFragment, UnitFileLine};
+
+mod service_unit;
+pub use service_unit::{Restart, ServiceType, ServiceUnit};
+
+mod drop_in;
+pub use drop_in::DropIn;
+
+mod version;
+pub use version::SystemdVersion;
+
+mod cgroup;
+pub use cgroup::ControlGroup;
+
+mod dependency;
+pub use dependency::{DependencyKind, DependencyNode, DependencyOptions};
+
+mod graph;
+pub use graph::DependencyGraph;
+
+mod wait;
+pub use wait::{UnitState, WaitCondition};
+
+mod restart;
+pub use restart::{HealthCheck, RestartReport};
+
+mod batch;
+pub use batch::BatchResult;
+
+mod journal;
+#[cfg(feature = "async")]
+pub use journal::JournalStream;
+pub use journal::{Journal, JournalEntry, JournalFollower, JournalQuery, Priority};
+
+mod transient;
+pub use transient::{TransientRun, TransientUnit};
+
+mod watch;
+#[cfg(feature = "async")]
+pub use watch::WatcherStream;
+pub use watch::{UnitEvent, Watcher};
+
+mod status;
+
+mod show;
+
 /// Struct with API calls to systemctl.
 ///
 /// Use the `::default()` impl if you don't need special arguments.
 ///
-/// Use the builder API when you want to specify a custom path to systemctl binary or extra args.
+/// Use the builder API when you want to specify a custom path to systemctl binary,
+/// the service manager [Scope] or extra args.
 #[derive(Builder, Default, Clone, Debug)]
 pub struct SystemCtl {
-    /// Allows passing global arguments to systemctl like `--user`.
+    /// Allows passing global arguments to systemctl like `--all`.
+    /// Prefer [Scope] over `--user` / `--global`.
+    #[builder(default)]
     additional_args: Vec<String>,
+    /// Service manager to operate on, [Scope::System] by default
+    #[builder(default)]
+    scope: Scope,
     /// The path to the systemctl binary, by default it's [SYSTEMCTL_PATH]
     path: Option<String>,
+    /// Operates offline on the given root directory (`--root`),
+    /// typically a chroot tree or an image being built.
+    /// Only commands that do not require a running service manager are allowed.
+    root: Option<String>,
+    /// Operates offline on the given disk image (`--image`).
+    /// Only commands that do not require a running service manager are allowed.
+    image: Option<String>,
+    /// Operates on a remote host or a local container rather than this host
+    target: Option<Target>,
+    /// Environment variables to run systemctl with.
+    /// They override the defaults: `LC_ALL=C`, `SYSTEMD_COLORS=0` and `SYSTEMD_PAGER=`.
+    #[builder(default)]
+    environment: HashMap<String, String>,
+    /// systemd version, detected on first use
+    #[builder(skip)]
+    version: OnceLock<SystemdVersion>,
 }
 
 impl SystemCtl {
@@ -35,8 +151,53 @@ impl SystemCtl {
         &'s self,
         args: S,
     ) -> std::io::Result<Child> {
+        let args: Vec<&str> = args.into_iter().collect();
+        let command = args.iter().find(|arg| !arg.starts_with('-'));
+        let mut offline_args = Vec::new();
+        if let Some(root) = &self.root {
+            offline_args.push(format!("--root={root}"));
+        }
+        if let Some(image) = &self.image {
+            offline_args.push(format!("--image={image}"));
+        }
+        if self.target.is_some() {
+            if !offline_args.is_empty() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "A remote target cannot be combined with --root/--image",
+                ));
+            }
+            if let Scope::OtherUser(_) = &self.scope {
+                // another user is reached with --machine=<user>@.host
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "A remote target cannot be combined with another user scope",
+                ));
+            }
+            if let Some(command) = command.filter(|cmd| LOCAL_COMMANDS.contains(cmd)) {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("\"{command}\" is not available on remote targets"),
+                ));
+            }
+        }
+        if !offline_args.is_empty() {
+            if let Some(command) = command.filter(|cmd| !OFFLINE_COMMANDS.contains(cmd)) {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("\"{command}\" is not available offline (--root/--image)"),
+                ));
+            }
+        }
+        let target_args = self.target.as_ref().map(Target::args).unwrap_or_default();
         std::process::Command::new(self.get_path())
-            .args(self.additional_args.iter().map(String::as_str).chain(args))
+            .args(&self.additional_args)
+            .args(self.scope.args())
+            .args(target_args)
+            .args(offline_args)
+            .args(args)
+            .envs(DEFAULT_ENVIRONMENT.iter().copied())
+            .envs(&self.environment)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::null())
             .spawn()
@@ -46,6 +207,186 @@ impl SystemCtl {
         self.path.as_deref().unwrap_or(SYSTEMCTL_PATH)
     }
 
+    /// Returns `true` when operating on a root directory or image
+    pub fn is_offline(&self) -> bool {
+        self.root.is_some() || self.image.is_some()
+    }
+
+    /// Returns the service manager scope
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+
+    /// Returns a [Journal] reading the logs of the service manager this instance
+    /// operates on: same scope, container, root directory or image
+    pub fn journal(&self) -> std::io::Result<Journal> {
+        let machine = match &self.target {
+            None => None,
+            Some(Target::Machine(machine)) => Some(machine.clone()),
+            Some(Target::Host { .. }) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "journalctl cannot read the journal of a remote host",
+                ))
+            },
+        };
+        let user = match &self.scope {
+            Scope::System => false,
+            Scope::User => true,
+            scope => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!("journalctl cannot read the journal of {scope:?} scope"),
+                ))
+            },
+        };
+        Ok(Journal::builder()
+            .user(user)
+            .maybe_machine(machine)
+            .maybe_root(self.root.clone())
+            .maybe_image(self.image.clone())
+            .environment(self.environment.clone())
+            .build())
+    }
+
+    /// Runs `unit` as a transient unit with `systemd-run`,
+    /// on the service manager this instance operates on.
+    /// Returns once the unit started, or terminated with [TransientUnit::wait],
+    /// [TransientUnit::pipe] or [TransientUnit::scope].
+    pub fn run_transient(&self, unit: &TransientUnit) -> std::io::Result<TransientRun> {
+        if self.is_offline() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "systemd-run needs a running service manager, not --root/--image",
+            ));
+        }
+        match (&self.scope, &self.target) {
+            (Scope::Global, _) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "systemd-run cannot run units for all users",
+                ))
+            },
+            (Scope::OtherUser(_), Some(_)) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "A remote target cannot be combined with another user scope",
+                ))
+            },
+            _ => {},
+        }
+        let target_args = self.target.as_ref().map(Target::args).unwrap_or_default();
+        let mut command = std::process::Command::new(unit.get_path());
+        command
+            .args(unit.additional_args())
+            .args(self.scope.args())
+            .args(target_args)
+            .args(unit.args()?)
+            .stdin(std::process::Stdio::null());
+        if !unit.scope {
+            // scope commands are run by systemd-run itself and inherit its environment,
+            // use [TransientUnit::environment] for them
+            command
+                .envs(DEFAULT_ENVIRONMENT.iter().copied())
+                .envs(&self.environment);
+        }
+        let output = command.output()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(Error::other(format!(
+                "systemd-run failed with {}: {}",
+                output.status,
+                stderr.trim()
+            )));
+        }
+        let mut run = match (TransientRun::from_stderr(&stderr), unit.unit_name()) {
+            (Some(run), _) => run,
+            (None, Some(name)) => TransientRun {
+                unit: name,
+                ..Default::default()
+            },
+            (None, None) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "systemd-run did not report the unit name",
+                ))
+            },
+        };
+        // older systemd-run do not report it, terminated units are unloaded
+        let running = !(unit.scope || unit.wait || unit.pipe);
+        if run.invocation_id.is_none() && running {
+            run.invocation_id = self
+                .show(ServiceProperty::InvocationID, &run.unit)
+                .ok()
+                .flatten()
+                .filter(|id| !id.is_empty());
+        }
+        run.stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        Ok(run)
+    }
+
+    /// Returns the remote target, if any
+    pub fn target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    /// Returns the systemd version and compile time features, from `systemctl --version`.
+    /// The version is detected once, then cached on this instance.
+    /// This is the version of the local systemctl binary, even for remote targets.
+    pub fn version(&self) -> std::io::Result<&SystemdVersion> {
+        if let Some(version) = self.version.get() {
+            return Ok(version);
+        }
+        let version = SystemdVersion::from_str(&self.systemctl_capture(["--version"])?)?;
+        Ok(self.version.get_or_init(|| version))
+    }
+
+    /// Returns `true` if listings can be retrieved with `--output=json`
+    fn supports_json(&self) -> bool {
+        self.version()
+            .is_ok_and(SystemdVersion::supports_json_output)
+    }
+
+    /// Fails with `ErrorKind::Unsupported` if the detected systemd version
+    /// does not match `supported`. Unknown versions are not rejected.
+    fn require(
+        &self,
+        supported: fn(&SystemdVersion) -> bool,
+        operation: &str,
+    ) -> std::io::Result<()> {
+        match self.version() {
+            Ok(version) if !supported(version) => Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "\"{operation}\" is not supported by systemd {}",
+                    version.version
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the unit directory of `scope`, within the root directory if any
+    fn unit_directory(&self, scope: &Scope) -> std::io::Result<PathBuf> {
+        if self.target.is_some() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Unit files cannot be written on remote targets",
+            ));
+        }
+        if self.image.is_some() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Unit files cannot be written into an --image",
+            ));
+        }
+        let directory = scope.unit_directory()?;
+        Ok(match &self.root {
+            Some(root) => Path::new(root).join(directory.strip_prefix("/").unwrap_or(&directory)),
+            None => directory,
+        })
+    }
+
     /// Invokes `systemctl $args` silently
     fn systemctl<'a, 's: 'a, S: IntoIterator<Item = &'a str>>(
         &'s self,
@@ -59,7 +400,23 @@ impl SystemCtl {
         &'s self,
         args: S,
     ) -> std::io::Result<String> {
-        let child = self.spawn_child(args)?;
+        let output = self.systemctl_capture_listing(args)?;
+        if output.is_empty() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "systemctl stdout empty",
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Invokes `systemctl $args` listing units and captures stdout stream,
+    /// which is empty when nothing is listed as the legend is omitted
+    fn systemctl_capture_listing<'a, 's: 'a, S: IntoIterator<Item = &'a str>>(
+        &'s self,
+        args: S,
+    ) -> std::io::Result<String> {
+        let child = self.spawn_child(CAPTURE_ARGS.iter().copied().chain(args))?;
         let output = child.wait_with_output()?;
         match output.status.code() {
             Some(0) => {}, // success
@@ -84,25 +441,8 @@ impl SystemCtl {
             },
         }
 
-        let stdout: Vec<u8> = output.stdout;
-        let size = stdout.len();
-
-        if size > 0 {
-            return if let Ok(s) = String::from_utf8(stdout) {
-                Ok(s)
-            } else {
-                Err(Error::new(
-                    ErrorKind::InvalidData,
-                    "Invalid utf8 data in stdout",
-                ))
-            };
-        }
-
-        // if this is reached all if's above did not work
-        Err(Error::new(
-            ErrorKind::UnexpectedEof,
-            "systemctl stdout empty",
-        ))
+        String::from_utf8(output.stdout)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid utf8 data in stdout"))
     }
 
     /// Reloads all unit files
@@ -115,6 +455,70 @@ impl SystemCtl {
         self.systemctl(["restart", unit])
     }
 
+    /// Restarts given `unit`, then samples it at regular intervals during `settle_time`
+    /// to verify that it stays up: not failed, not restarted meanwhile
+    /// according to its `InvocationID` and `NRestarts` properties,
+    /// and passing the caller health `checks`.
+    /// Sampling stops as soon as one of those fails, or the unit state
+    /// cannot be retrieved anymore, see [RestartReport::sampling_error].
+    pub fn restart_verified<C: HealthCheck>(
+        &self,
+        unit: &str,
+        settle_time: Duration,
+        checks: C,
+    ) -> std::io::Result<RestartReport> {
+        let status = self.restart(unit)?;
+        if !status.success() {
+            return Err(Error::other(format!(
+                "Failed to restart \"{unit}\": {status}"
+            )));
+        }
+        let property = restart::restart_properties();
+        let properties = || -> std::io::Result<BTreeMap<String, String>> {
+            let output = self.systemctl_capture(["show", "--property", property.as_str(), unit])?;
+            show::parse_properties(&output)
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "empty show output"))
+        };
+        let mut report = RestartReport::new(unit, &properties()?);
+        for _ in 0..restart::SETTLE_SAMPLES {
+            std::thread::sleep(settle_time / restart::SETTLE_SAMPLES);
+            match properties() {
+                Ok(properties) => {
+                    if !report.record(&properties) || !report.record_check(&checks) {
+                        break;
+                    }
+                },
+                Err(e) => {
+                    report.sampling_error = Some(e.to_string());
+                    break;
+                },
+            }
+        }
+        Ok(report)
+    }
+
+    /// Same as [Self::restart_verified], calling `rollback`
+    /// if the unit did not stay up during `settle_time`
+    pub fn restart_verified_or_rollback<C, F>(
+        &self,
+        unit: &str,
+        settle_time: Duration,
+        checks: C,
+        rollback: F,
+    ) -> std::io::Result<RestartReport>
+    where
+        C: HealthCheck,
+        F: FnOnce(&RestartReport) -> std::io::Result<()>,
+    {
+        let report = self.restart_verified(unit, settle_time, checks)?;
+        if !report.is_healthy() {
+            rollback(&report)?;
+        }
+        Ok(report)
+    }
+
     /// Forces given `unit` to start
     pub fn start(&self, unit: &str) -> std::io::Result<ExitStatus> {
         self.systemctl(["start", unit])
@@ -145,6 +549,142 @@ impl SystemCtl {
         self.systemctl(["disable", unit])
     }
 
+    /// Starts all given `units` with a single systemctl invocation,
+    /// returning the outcome of each unit
+    pub fn start_many(&self, units: &[&str]) -> BatchResult {
+        self.batch(batch::Batch::Start, units)
+    }
+
+    /// Stops all given `units` with a single systemctl invocation,
+    /// returning the outcome of each unit
+    pub fn stop_many(&self, units: &[&str]) -> BatchResult {
+        self.batch(batch::Batch::Stop, units)
+    }
+
+    /// Restarts all given `units` with a single systemctl invocation,
+    /// returning the outcome of each unit
+    pub fn restart_many(&self, units: &[&str]) -> BatchResult {
+        self.batch(batch::Batch::Restart, units)
+    }
+
+    /// Enables all given `units` with a single systemctl invocation.
+    /// systemctl enables none of them if one fails, they are then
+    /// enabled one by one to find out which ones fail.
+    pub fn enable_many(&self, units: &[&str]) -> BatchResult {
+        if units.is_empty() {
+            return BatchResult::new();
+        }
+        let all = self.systemctl(["enable"].into_iter().chain(units.iter().copied()));
+        if all.as_ref().is_ok_and(ExitStatus::success) {
+            return units
+                .iter()
+                .map(|unit| (unit.to_string(), Ok(())))
+                .collect();
+        }
+        batch::parallel(units, |unit| {
+            batch::status_result("enable", unit, self.enable(unit)?)
+        })
+    }
+
+    /// Runs `batch` on all `units` with a single systemctl invocation.
+    /// As systemctl carries on with other units when one fails,
+    /// failures are attributed to units from their properties before and after it.
+    fn batch(&self, batch: batch::Batch, units: &[&str]) -> BatchResult {
+        let command = batch.command();
+        if units.is_empty() {
+            return BatchResult::new();
+        }
+        let property = batch::batch_properties();
+        let properties = || {
+            let output = self
+                .systemctl_capture(
+                    ["show", "--property", property.as_str()]
+                        .into_iter()
+                        .chain(units.iter().copied()),
+                )
+                .ok()?;
+            let properties = show::parse_properties(&output);
+            (properties.len() == units.len()).then_some(properties)
+        };
+        let before = if batch.needs_before() {
+            match properties() {
+                Some(before) => before,
+                // properties unknown, fall back to one invocation per unit
+                None => {
+                    return batch::parallel(units, |unit| {
+                        batch::status_result(command, unit, self.systemctl([command, unit])?)
+                    })
+                },
+            }
+        } else {
+            vec![BTreeMap::new(); units.len()]
+        };
+        let all = self.systemctl([command].into_iter().chain(units.iter().copied()));
+        if all.as_ref().is_ok_and(ExitStatus::success) {
+            return units
+                .iter()
+                .map(|unit| (unit.to_string(), Ok(())))
+                .collect();
+        }
+        let Some(after) = properties() else {
+            // outcome unknown, running the operation again could restart units twice
+            return units
+                .iter()
+                .map(|unit| {
+                    let result = match &all {
+                        Ok(status) => batch::status_result(command, unit, *status),
+                        Err(e) => Err(Error::new(e.kind(), e.to_string())),
+                    };
+                    (unit.to_string(), result)
+                })
+                .collect();
+        };
+        units
+            .iter()
+            .zip(before.iter().zip(after.iter()))
+            .map(|(unit, (before, after))| {
+                let property = |key: &str| after.get(key).map_or("", String::as_str);
+                let result = if batch.succeeded(before, after) {
+                    Ok(())
+                } else {
+                    Err(Error::other(format!(
+                        "Failed to {command} \"{unit}\": unit is {}, {} ({})",
+                        property("LoadState"),
+                        property("ActiveState"),
+                        property("SubState")
+                    )))
+                };
+                (unit.to_string(), result)
+            })
+            .collect()
+    }
+
+    /// Masks given `unit`, making it impossible to start
+    pub fn mask(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.systemctl(["mask", unit])
+    }
+
+    /// Unmasks given `unit`
+    pub fn unmask(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.systemctl(["unmask", unit])
+    }
+
+    /// Enables or disables given `unit` according to the preset policy
+    pub fn preset(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.systemctl(["preset", unit])
+    }
+
+    /// Returns the default target, booted into
+    pub fn get_default(&self) -> std::io::Result<String> {
+        let target = self.systemctl_capture(["get-default"])?;
+        Ok(target.trim_end().to_string())
+    }
+
+    /// Sets the default target to boot into
+    pub fn set_default(&self, target: &str) -> std::io::Result<ExitStatus> {
+        self.systemctl(["set-default", target])
+    }
+
     /// Returns raw status from `systemctl status $unit` call
     pub fn status(&self, unit: &str) -> std::io::Result<String> {
         self.systemctl_capture(["status", unit])
@@ -155,6 +695,90 @@ impl SystemCtl {
         self.systemctl_capture(["cat", unit])
     }
 
+    /// Parses systemctl `cat` output of given `unit`
+    /// into a [UnitFile], made of its fragment and drop-ins
+    pub fn unit_file(&self, unit: &str) -> std::io::Result<UnitFile> {
+        Ok(UnitFile::from_cat(&self.cat(unit)?))
+    }
+
+    /// Writes given `unit` file into the unit directory of `scope`,
+    /// within the root directory when operating offline,
+    /// then reloads the matching service manager.
+    /// Returns the path of the installed unit file.
+    pub fn install_unit(&self, unit: &ServiceUnit, scope: Scope) -> std::io::Result<PathBuf> {
+        let directory = self.unit_directory(&scope)?;
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(unit.file_name());
+        // written aside then renamed, so the unit is never seen truncated
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, unit.to_string())?;
+        std::fs::rename(&tmp, &path)?;
+        if self.is_offline() || scope == Scope::Global {
+            // no service manager to reload
+            return Ok(path);
+        }
+        let manager = Self {
+            scope,
+            ..self.clone()
+        };
+        let status = manager.daemon_reload()?;
+        if !status.success() {
+            return Err(Error::other(format!("daemon-reload failed: {status}")));
+        }
+        Ok(path)
+    }
+
+    /// Lists drop-ins of given `unit` in the unit directory of `scope`,
+    /// in the order systemd applies them.
+    /// The merged configuration is available with [Self::cat].
+    pub fn list_drop_ins(&self, unit: &str, scope: Scope) -> std::io::Result<Vec<DropIn>> {
+        DropIn::list(&self.unit_directory(&scope)?, unit)
+    }
+
+    /// Reads drop-in `name` of given `unit` in the unit directory of `scope`
+    pub fn read_drop_in(&self, unit: &str, name: &str, scope: Scope) -> std::io::Result<DropIn> {
+        DropIn::read(&self.unit_directory(&scope)?, unit, name)
+    }
+
+    /// Creates or replaces drop-in `name` of given `unit` in the unit directory of `scope`.
+    /// Call [Self::daemon_reload] for the changes to take effect.
+    pub fn write_drop_in(
+        &self,
+        unit: &str,
+        name: &str,
+        content: &UnitFile,
+        scope: Scope,
+    ) -> std::io::Result<DropIn> {
+        DropIn::write(&self.unit_directory(&scope)?, unit, name, content)
+    }
+
+    /// Updates drop-in `name` of given `unit` in the unit directory of `scope`
+    /// with `update`, creating it when it does not exist yet.
+    /// Call [Self::daemon_reload] for the changes to take effect.
+    pub fn update_drop_in<F: FnOnce(&mut UnitFile)>(
+        &self,
+        unit: &str,
+        name: &str,
+        scope: Scope,
+        update: F,
+    ) -> std::io::Result<DropIn> {
+        let directory = self.unit_directory(&scope)?;
+        let mut content = match DropIn::read(&directory, unit, name) {
+            Ok(drop_in) => drop_in.content,
+            Err(e) if e.kind() == ErrorKind::NotFound => UnitFile::default(),
+            Err(e) => return Err(e),
+        };
+        update(&mut content);
+        DropIn::write(&directory, unit, name, &content)
+    }
+
+    /// Deletes drop-in `name` of given `unit` in the unit directory of `scope`.
+    /// Call [Self::daemon_reload] for the changes to take effect.
+    pub fn remove_drop_in(&self, unit: &str, name: &str, scope: Scope) -> std::io::Result<()> {
+        DropIn::remove(&self.unit_directory(&scope)?, unit, name)
+    }
+
     /// Returns `true` if given `unit` is actively running
     pub fn is_active(&self, unit: &str) -> std::io::Result<bool> {
         let status = self.systemctl_capture(["is-active", unit])?;
@@ -173,6 +797,101 @@ impl SystemCtl {
         )
     }
 
+    /// Returns the [UnitState] of each of given `units`, in the same order,
+    /// with a single `systemctl show` call
+    pub fn unit_states(&self, units: &[&str]) -> std::io::Result<Vec<UnitState>> {
+        if units.is_empty() {
+            return Ok(Vec::new());
+        }
+        let property = wait::state_properties();
+        let output = self.systemctl_capture(
+            ["show", "--property", property.as_str()]
+                .into_iter()
+                .chain(units.iter().copied()),
+        )?;
+        let states: Vec<UnitState> = show::parse_properties(&output)
+            .iter()
+            .map(UnitState::from_properties)
+            .collect();
+        if states.len() != units.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Expected the state of {} units, got {}",
+                    units.len(),
+                    states.len()
+                ),
+            ));
+        }
+        Ok(states)
+    }
+
+    /// Waits until given `unit` meets `condition`, a target [ActiveState]
+    /// or a predicate on its [UnitState], polling every `poll_interval`.
+    /// Returns the final state, or fails as soon as the unit enters the `failed` state
+    /// (unless awaited) and with `ErrorKind::TimedOut` once `timeout` elapsed.
+    pub fn wait_for<C: WaitCondition>(
+        &self,
+        unit: &str,
+        condition: C,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> std::io::Result<UnitState> {
+        let mut states = self.wait_for_all(&[unit], condition, timeout, poll_interval)?;
+        states
+            .remove(unit)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Missing unit state"))
+    }
+
+    /// Waits until all given `units` meet `condition`, see [Self::wait_for].
+    /// Returns the final state of each unit.
+    pub fn wait_for_all<C: WaitCondition>(
+        &self,
+        units: &[&str],
+        condition: C,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> std::io::Result<BTreeMap<String, UnitState>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let states = self.unit_states(units)?;
+            let mut pending = Vec::new();
+            for (unit, state) in units.iter().zip(states.iter()) {
+                if condition.is_met(state) {
+                    continue;
+                }
+                if state.active == ActiveState::Failed {
+                    return Err(Error::other(format!(
+                        "Unit \"{unit}\" entered the failed state ({})",
+                        state.sub_state
+                    )));
+                }
+                pending.push(*unit);
+            }
+            if pending.is_empty() {
+                return Ok(units
+                    .iter()
+                    .map(|unit| unit.to_string())
+                    .zip(states)
+                    .collect());
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::new(
+                    ErrorKind::TimedOut,
+                    format!("Timed out waiting for {}", pending.join(", ")),
+                ));
+            }
+            std::thread::sleep(poll_interval.min(deadline - now));
+        }
+    }
+
+    /// Returns a [Watcher] reporting state transitions of `units`,
+    /// unit names or glob patterns like `nginx*`, polled every `poll_interval`
+    pub fn watch(&self, units: &[&str], poll_interval: Duration) -> Watcher {
+        Watcher::new(self.clone(), units, poll_interval)
+    }
+
     /// Returns a list of services that are dependencies of the given unit
     pub fn list_dependencies(&self, unit: &str) -> std::io::Result<Vec<String>> {
         let output = self.systemctl_capture(vec!["list-dependencies", unit])?;
@@ -189,6 +908,69 @@ impl SystemCtl {
         Ok(dependencies)
     }
 
+    /// Returns the dependency tree of given unit, see [DependencyOptions].
+    /// The state of every unit and the dependency kinds are retrieved
+    /// with as few `systemctl show` calls as possible.
+    pub fn list_dependencies_tree(
+        &self,
+        unit: &str,
+        options: &DependencyOptions,
+    ) -> std::io::Result<DependencyNode> {
+        let mut args = options.args()?;
+        args.push(unit);
+        let mut tree = DependencyNode::from_raw(&self.systemctl_capture(args)?)?;
+
+        let followed = options.properties();
+        let property = ["Id", "ActiveState"]
+            .into_iter()
+            .chain(followed.iter().copied())
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut units = tree.units();
+        units.sort_unstable();
+        units.dedup();
+        let units = self
+            .show_chunked(&property, &units)?
+            .into_iter()
+            .filter_map(|properties| Some((properties.get("Id")?.clone(), properties)))
+            .collect();
+        tree.apply(&units, &followed);
+        Ok(tree)
+    }
+
+    /// Builds the [DependencyGraph] of all loaded units
+    pub fn dependency_graph(&self) -> std::io::Result<DependencyGraph> {
+        let mut args = vec!["list-units", "--all"];
+        let units = if self.supports_json() {
+            args.push("--output=json");
+            Self::list_units_full_from_json(self.systemctl_capture_listing(args)?)?
+        } else {
+            Self::list_units_full_from_raw(self.systemctl_capture_listing(args)?)?
+        };
+        let units: Vec<&str> = units.iter().map(|unit| unit.unit_name.as_str()).collect();
+        let properties = self.show_chunked(graph::GRAPH_PROPERTIES, &units)?;
+        Ok(DependencyGraph::from_properties(&properties))
+    }
+
+    /// Returns the `property` list of each of given `units`,
+    /// with one `systemctl show` call per [SHOW_CHUNK] units
+    fn show_chunked(
+        &self,
+        property: &str,
+        units: &[&str],
+    ) -> std::io::Result<Vec<BTreeMap<String, String>>> {
+        let mut properties = Vec::with_capacity(units.len());
+        for units in units.chunks(SHOW_CHUNK) {
+            let output = self.systemctl_capture(
+                ["show", "--property", property]
+                    .into_iter()
+                    .chain(units.iter().copied()),
+            )?;
+            properties.extend(show::parse_properties(&output));
+        }
+        Ok(properties)
+    }
+
     /// Isolates given unit, only self and its dependencies are
     /// now actively running
     pub fn isolate(&self, unit: &str) -> std::io::Result<ExitStatus> {
@@ -196,17 +978,26 @@ impl SystemCtl {
     }
 
     /// Freezes (halts) given unit.
-    /// This operation might not be feasible.
+    /// This operation might not be feasible, and requires systemd 246.
     pub fn freeze(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.require(SystemdVersion::supports_freeze, "freeze")?;
         self.systemctl(["freeze", unit])
     }
 
     /// Unfreezes given unit (recover from halted state).
-    /// This operation might not be feasible.
+    /// This operation might not be feasible, and requires systemd 246.
     pub fn unfreeze(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.require(SystemdVersion::supports_freeze, "thaw")?;
         self.systemctl(["thaw", unit])
     }
 
+    /// Removes the cache and runtime directories of given unit.
+    /// Requires systemd 243.
+    pub fn clean(&self, unit: &str) -> std::io::Result<ExitStatus> {
+        self.require(SystemdVersion::supports_clean, "clean")?;
+        self.systemctl(["clean", unit])
+    }
+
     /// Returns `true` if given `unit` exists,
     /// ie., service could be or is actively deployed
     /// and manageable by systemd
@@ -237,19 +1028,46 @@ impl SystemCtl {
         if let Some(glob) = glob {
             args.push(glob)
         }
-        let mut result: Vec<UnitList> = Vec::new();
-        let content = self.systemctl_capture(args)?;
-        let lines = content
-            .lines()
-            .filter(|line| line.contains('.') && !line.ends_with('.'));
+        if self.supports_json() {
+            args.push("--output=json");
+            let content = self.systemctl_capture_listing(args)?;
+            return Self::list_unit_files_full_from_json(content);
+        }
+        let content = self.systemctl_capture_listing(args)?;
+        // systemd < 245 lists no vendor preset column
+        let preset_column = self
+            .version()
+            .map_or(true, SystemdVersion::has_vendor_preset_column);
+        Self::list_unit_files_full_from_raw(content, preset_column)
+    }
 
-        for l in lines {
+    /// Parses `systemctl list-unit-files --no-legend` output.
+    /// `preset_column` tells whether it has the `VENDOR PRESET` column,
+    /// which systemd < 245 does not list.
+    pub fn list_unit_files_full_from_raw(
+        raw: String,
+        preset_column: bool,
+    ) -> std::io::Result<Vec<UnitList>> {
+        let mut result: Vec<UnitList> = Vec::new();
+        for l in raw.lines() {
+            if l.trim().is_empty() {
+                continue;
+            }
             let parsed: Vec<&str> = l.split_ascii_whitespace().collect();
-            let vendor_preset = match parsed[2] {
-                "-" => None,
-                "enabled" => Some(true),
-                "disabled" => Some(false),
-                _ => None,
+            if parsed.len() < 2 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Malformed unit file line \"{l}\""),
+                ));
+            }
+            let vendor_preset = if preset_column {
+                match parsed.get(2) {
+                    Some(&"enabled") => Some(true),
+                    Some(&"disabled") => Some(false),
+                    _ => None,
+                }
+            } else {
+                None
             };
             result.push(UnitList {
                 unit_file: parsed[0].to_string(),
@@ -282,10 +1100,88 @@ impl SystemCtl {
         if let Some(glob) = glob {
             args.push(glob)
         }
-        let content = self.systemctl_capture(args)?;
+        if self.supports_json() {
+            args.push("--output=json");
+            let content = self.systemctl_capture_listing(args)?;
+            return Self::list_units_full_from_json(content);
+        }
+        let content = self.systemctl_capture_listing(args)?;
         Self::list_units_full_from_raw(content)
     }
 
+    /// Parses a JSON array of objects, as produced by `--output=json` listings
+    fn parse_json_listing(raw: &str) -> std::io::Result<Vec<serde_json::Value>> {
+        if raw.trim().is_empty() {
+            // no output at all for empty listings
+            return Ok(Vec::new());
+        }
+        match serde_json::from_str(raw) {
+            Ok(serde_json::Value::Array(items)) => Ok(items),
+            Ok(_) => Err(Error::new(
+                ErrorKind::InvalidData,
+                "JSON listing is not an array",
+            )),
+            Err(e) => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid JSON listing: {e}"),
+            )),
+        }
+    }
+
+    /// Parses `systemctl list-unit-files --output=json` output
+    pub fn list_unit_files_full_from_json(raw: String) -> std::io::Result<Vec<UnitList>> {
+        let items = Self::parse_json_listing(&raw)?;
+        let field = |item: &serde_json::Value, key| {
+            item.get(key)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+        let mut result = Vec::with_capacity(items.len());
+        for item in items {
+            let unit_file = field(&item, "unit_file")
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "JSON unit file without name"))?;
+            let vendor_preset = match field(&item, "preset").as_deref() {
+                Some("enabled") => Some(true),
+                Some("disabled") => Some(false),
+                _ => None,
+            };
+            result.push(UnitList {
+                unit_file,
+                state: field(&item, "state").unwrap_or_default(),
+                vendor_preset,
+            })
+        }
+        Ok(result)
+    }
+
+    /// Parses `systemctl list-units --output=json` output
+    pub fn list_units_full_from_json(raw: String) -> std::io::Result<Vec<UnitService>> {
+        let items = Self::parse_json_listing(&raw)?;
+        let field = |item: &serde_json::Value, key| {
+            item.get(key)
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let mut result = Vec::with_capacity(items.len());
+        for item in items {
+            let unit_name = field(&item, "unit");
+            if unit_name.is_empty() {
+                return Err(Error::new(ErrorKind::InvalidData, "JSON unit without name"));
+            }
+            result.push(UnitService {
+                unit_name,
+                loaded: LoadedState::from_str(&field(&item, "load"))
+                    .unwrap_or(LoadedState::Unknown),
+                active: ActiveState::from_str(&field(&item, "active"))
+                    .unwrap_or(ActiveState::Unknown),
+                sub_state: field(&item, "sub"),
+                description: field(&item, "description"),
+            })
+        }
+        Ok(result)
+    }
+
     pub fn list_units_full_from_raw(raw: String) -> std::io::Result<Vec<UnitService>> {
         let mut result: Vec<UnitService> = Vec::new();
 
@@ -294,15 +1190,20 @@ impl SystemCtl {
             .filter(|line| line.contains('.') && !line.ends_with('.'));
 
         for l in lines {
-            // fixes format for not found units
-            let slice = if l.starts_with("● ") { &l[3..] } else { l };
-            let parsed: Vec<&str> = slice.split_ascii_whitespace().collect();
-
+            let parsed: Vec<&str> = l.split_ascii_whitespace().collect();
+            let (Some(unit_name), Some(loaded), Some(active), Some(sub_state)) =
+                (parsed.first(), parsed.get(1), parsed.get(2), parsed.get(3))
+            else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Malformed unit line \"{l}\""),
+                ));
+            };
             result.push(UnitService {
-                unit_name: parsed[0].to_string(),
-                loaded: LoadedState::from_str(parsed[1]).unwrap_or(LoadedState::Unknown),
-                active: ActiveState::from_str(parsed[2]).unwrap_or(ActiveState::Unknown),
-                sub_state: parsed[3].to_string(),
+                unit_name: unit_name.to_string(),
+                loaded: LoadedState::from_str(loaded).unwrap_or(LoadedState::Unknown),
+                active: ActiveState::from_str(active).unwrap_or(ActiveState::Unknown),
+                sub_state: sub_state.to_string(),
                 description: parsed[4..].join(" "),
             })
         }
@@ -358,141 +1259,86 @@ impl SystemCtl {
     }
 
     /// Builds a new `Unit` structure by retrieving
-    /// structure attributes with a `systemctl status $unit` call
+    /// structure attributes with a single `systemctl show $unit` call,
+    /// see [Unit::from_show], and `also` from its unit file with `systemctl cat`
     pub fn create_unit(&self, name: &str) -> std::io::Result<Unit> {
-        if let Ok(false) = self.exists(name) {
-            return Err(Error::new(
-                ErrorKind::NotFound,
-                format!("Unit or service \"{}\" does not exist", name),
-            ));
+        let output = self
+            .systemctl_capture(["show", name])
+            .map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => not_found(name),
+                _ => e,
+            })?;
+        if output.trim().is_empty() {
+            return Err(not_found(name));
         }
-        let mut u = Unit::default();
-        let status = self.status(name)?;
-        let mut lines = status.lines();
-        let next = lines.next().unwrap();
-        let (_, rem) = next.split_at(3);
-        let mut items = rem.split_ascii_whitespace();
-        let name_raw = items.next().unwrap().trim();
-        if let Some(delim) = items.next() {
-            if delim.trim().eq("-") {
-                // --> description string is provided
-                let items: Vec<_> = items.collect();
-                u.description = Some(itertools::join(&items, " "));
+        self.complete_unit(name, Unit::from_show(&output)?)
+    }
+
+    /// Builds the `Unit` structure of each of given `names`, in the same order,
+    /// with as few `systemctl show` calls as possible, see [Self::create_unit]
+    pub fn create_units(&self, names: &[&str]) -> Vec<std::io::Result<Unit>> {
+        let mut units = Vec::with_capacity(names.len());
+        for names in names.chunks(SHOW_CHUNK) {
+            let properties = self
+                .systemctl_capture(["show"].into_iter().chain(names.iter().copied()))
+                .map(|output| show::parse_properties(&output));
+            match properties {
+                Ok(properties) if properties.len() == names.len() => units.extend(
+                    names
+                        .iter()
+                        .zip(properties.iter())
+                        .map(|(name, properties)| {
+                            self.complete_unit(name, Unit::from_properties(properties)?)
+                        }),
+                ),
+                // blocks cannot be matched to units, retrieve them one by one
+                _ => units.extend(names.iter().map(|name| self.create_unit(name))),
             }
         }
-        let (name, utype_raw) = name_raw
-            .rsplit_once('.')
-            .expect("Unit is missing a Type, this should not happen!");
-        // `type` is deduced from .extension
-        u.utype = match Type::from_str(utype_raw) {
-            Ok(t) => t,
-            Err(e) => panic!("For {:?} -> {e}", name_raw),
-        };
-        let mut is_doc = false;
-        for line in lines {
-            let line = line.trim_start();
-            if let Some(line) = line.strip_prefix("Loaded: ") {
-                // Match and get rid of "Loaded: "
-                if let Some(line) = line.strip_prefix("loaded ") {
-                    u.loaded_state = LoadedState::Loaded;
-                    let line = line.strip_prefix('(').unwrap();
-                    let line = line.strip_suffix(')').unwrap();
-                    let items: Vec<&str> = line.split(';').collect();
-                    u.script = items[0].trim().to_string();
-                    u.auto_start = AutoStartStatus::from_str(items[1].trim())
-                        .unwrap_or(AutoStartStatus::Disabled);
-                    if items.len() > 2 {
-                        // preset is optionnal ?
-                        u.preset = items[2].trim().ends_with("enabled");
-                    }
-                } else if line.starts_with("masked") {
-                    u.loaded_state = LoadedState::Masked;
-                }
-            } else if let Some(line) = line.strip_prefix("Transient: ") {
-                if line == "yes" {
-                    u.transient = true
-                }
-            } else if line.starts_with("Active: ") {
-                // skip that one
-                // we already have .active() .inative() methods
-                // to access this information
-            } else if let Some(line) = line.strip_prefix("Docs: ") {
-                is_doc = true;
-                if let Ok(doc) = Doc::from_str(line) {
-                    u.docs.get_or_insert_with(Vec::new).push(doc);
-                }
-            } else if let Some(line) = line.strip_prefix("What: ") {
-                // mountpoint infos
-                u.mounted = Some(line.to_string())
-            } else if let Some(line) = line.strip_prefix("Where: ") {
-                // mountpoint infos
-                u.mountpoint = Some(line.to_string());
-            } else if let Some(line) = line.strip_prefix("Main PID: ") {
-                // example -> Main PID: 787 (gpm)
-                if let Some((pid, proc)) = line.split_once(' ') {
-                    u.pid = Some(pid.parse::<u64>().unwrap_or(0));
-                    u.process = Some(proc.replace(&['(', ')'][..], ""));
-                };
-            } else if let Some(line) = line.strip_prefix("Cntrl PID: ") {
-                // example -> Main PID: 787 (gpm)
-                if let Some((pid, proc)) = line.split_once(' ') {
-                    u.pid = Some(pid.parse::<u64>().unwrap_or(0));
-                    u.process = Some(proc.replace(&['(', ')'][..], ""));
-                };
-            } else if line.starts_with("Process: ") {
-                //TODO: implement
-                //TODO: parse as a Process item
-                //let items : Vec<_> = line.split_ascii_whitespace().collect();
-                //let proc_pid = u64::from_str_radix(items[1].trim(), 10).unwrap();
-                //let cli;
-                //Process: 640 ExecStartPre=/usr/sbin/sshd -t (code=exited, status=0/SUCCESS)
-            } else if line.starts_with("CGroup: ") {
-                //TODO: implement
-                //LINE: "CGroup: /system.slice/sshd.service"
-                //LINE: "└─1050 /usr/sbin/sshd -D"
-            } else if line.starts_with("Tasks: ") {
-                //TODO: implement
-            } else if let Some(line) = line.strip_prefix("Memory: ") {
-                u.memory = Some(line.trim().to_string());
-            } else if let Some(line) = line.strip_prefix("CPU: ") {
-                u.cpu = Some(line.trim().to_string())
-            } else {
-                // handling multi line cases
-                if is_doc {
-                    let line = line.trim_start();
-                    if let Ok(doc) = Doc::from_str(line) {
-                        u.docs.get_or_insert_with(Vec::new).push(doc);
-                    }
-                }
+        units
+    }
+
+    /// Rejects units that do not exist, and completes `u` with local process details
+    fn complete_unit(&self, name: &str, mut u: Unit) -> std::io::Result<Unit> {
+        if u.loaded_state == LoadedState::NotFound {
+            return Err(not_found(name));
+        }
+        if !u.script.is_empty() {
+            // `Also=` has no property, it is only known from the unit file
+            let utype: &str = u.utype.into();
+            if let Ok(unit_file) = self.unit_file(&format!("{}.{utype}", u.name)) {
+                let also: Vec<String> = unit_file
+                    .get_all("Install", "Also")
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect();
+                u.also = Some(also).filter(|also| !also.is_empty());
             }
         }
-
-        if let Ok(content) = self.cat(name_raw) {
-            let line_tuple = content
-                .lines()
-                .filter_map(|line| line.split_once('=').to_owned());
-            for (k, v) in line_tuple {
-                let val = v.to_string();
-                match k {
-                    "Wants" => u.wants.get_or_insert_with(Vec::new).push(val),
-                    "WantedBy" => u.wanted_by.get_or_insert_with(Vec::new).push(val),
-                    "Also" => u.also.get_or_insert_with(Vec::new).push(val),
-                    "Before" => u.before.get_or_insert_with(Vec::new).push(val),
-                    "After" => u.after.get_or_insert_with(Vec::new).push(val),
-                    "ExecStart" => u.exec_start = Some(val),
-                    "ExecReload" => u.exec_reload = Some(val),
-                    "Restart" => u.restart_policy = Some(val),
-                    "KillMode" => u.kill_mode = Some(val),
-                    _ => {},
+        if self.target.is_none() {
+            // main process name and control group processes, only available locally
+            u.process = u
+                .pid
+                .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+                .map(|comm| comm.trim_end().to_string());
+            if let Some(cgroup) = u.cgroup.as_mut() {
+                if let Ok(read) = ControlGroup::read(&cgroup.path) {
+                    *cgroup = read;
                 }
             }
         }
-
-        u.active = self.is_active(name_raw)?;
-        u.name = name.to_string();
         Ok(u)
     }
 
+    /// Returns all properties of given `unit`, from `systemctl show $unit`
+    pub fn show_properties(&self, unit: &str) -> std::io::Result<BTreeMap<String, String>> {
+        let output = self.systemctl_capture(["show", unit])?;
+        show::parse_properties(&output)
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "empty show output"))
+    }
+
     /// Show service property using systemctl show --property
     pub fn show(&self, property: ServiceProperty, unit: &str) -> std::io::Result<Option<String>> {
         let mut content =
@@ -561,7 +1407,7 @@ pub enum AutoStartStatus {
 }
 
 /// `Type` describes a Unit declaration Type in systemd
-#[derive(Copy, Clone, PartialEq, Eq, EnumString, Debug, Default)]
+#[derive(Copy, Clone, PartialEq, Eq, EnumString, IntoStaticStr, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Type {
     #[strum(serialize = "automount")]
@@ -585,6 +1431,8 @@ pub enum Type {
     Target,
     #[strum(serialize = "swap")]
     Swap,
+    #[strum(serialize = "device")]
+    Device,
 }
 
 /// `LoadedState` describes a Unit's current loaded state
@@ -597,6 +1445,12 @@ pub enum LoadedState {
     Masked,
     #[strum(serialize = "loaded", to_string = "Loaded")]
     Loaded,
+    #[strum(serialize = "not-found", to_string = "NotFound")]
+    NotFound,
+    #[strum(serialize = "bad-setting", to_string = "BadSetting")]
+    BadSetting,
+    #[strum(serialize = "error", to_string = "Error")]
+    Error,
     #[strum(serialize = "", to_string = "Unknown")]
     Unknown,
 }
@@ -623,30 +1477,241 @@ pub enum ActiveState {
     Unknown,
 }
 
-/*
-/// Process
-#[derive(Clone, Debug)]
+/// `Scope` describes which service manager and unit directory
+/// an operation applies to
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Scope {
+    /// System service manager, units in `/etc/systemd/system`
+    #[default]
+    System,
+    /// Calling user service manager, units in `~/.config/systemd/user`
+    User,
+    /// Service manager of given user, reached with `--user --machine=<user>@.host`,
+    /// units in `~<user>/.config/systemd/user`
+    OtherUser(String),
+    /// All users, units in `/etc/systemd/user`.
+    /// Only unit file operations (`enable`, `mask`...) apply to this scope.
+    Global,
+}
+
+impl Scope {
+    /// Returns the systemctl arguments selecting this scope
+    fn args(&self) -> Vec<String> {
+        match self {
+            Self::System => Vec::new(),
+            Self::User => vec!["--user".to_string()],
+            Self::OtherUser(user) => vec!["--user".to_string(), format!("--machine={user}@.host")],
+            Self::Global => vec!["--global".to_string()],
+        }
+    }
+
+    /// Returns the administrator unit directory of this scope
+    pub fn unit_directory(&self) -> std::io::Result<PathBuf> {
+        match self {
+            Self::System => Ok(PathBuf::from("/etc/systemd/system")),
+            Self::Global => Ok(PathBuf::from("/etc/systemd/user")),
+            Self::User => {
+                let config = std::env::var_os("XDG_CONFIG_HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .or_else(|| {
+                        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+                    })
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::NotFound,
+                            "Neither XDG_CONFIG_HOME nor HOME is defined",
+                        )
+                    })?;
+                Ok(config.join("systemd/user"))
+            },
+            Self::OtherUser(user) => {
+                let passwd = std::fs::read_to_string("/etc/passwd")?;
+                Self::home_directory(&passwd, user)
+                    .map(|home| home.join(".config/systemd/user"))
+                    .ok_or_else(|| {
+                        Error::new(ErrorKind::NotFound, format!("Unknown user \"{user}\""))
+                    })
+            },
+        }
+    }
+
+    /// Returns home directory of `user` from `/etc/passwd` content
+    fn home_directory(passwd: &str, user: &str) -> Option<PathBuf> {
+        passwd.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() > 5 && fields[0] == user {
+                Some(PathBuf::from(fields[5]))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// `Target` describes a remote host or container to operate on
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Target {
+    /// Remote host reached over SSH (`--host`),
+    /// optionally a container running on that host
+    Host {
+        /// Optional user name to connect as
+        user: Option<String>,
+        /// Host name or address
+        host: String,
+        /// Optional container name on that host
+        container: Option<String>,
+    },
+    /// Local container, like an nspawn machine (`--machine`)
+    Machine(String),
+}
+
+impl Target {
+    /// Returns the systemctl arguments selecting this target
+    fn args(&self) -> Vec<String> {
+        match self {
+            Self::Host {
+                user,
+                host,
+                container,
+            } => {
+                let mut target = String::new();
+                if let Some(user) = user {
+                    target.push_str(user);
+                    target.push('@');
+                }
+                target.push_str(host);
+                if let Some(container) = container {
+                    target.push(':');
+                    target.push_str(container);
+                }
+                vec!["-H".to_string(), target]
+            },
+            Self::Machine(name) => vec!["-M".to_string(), name.clone()],
+        }
+    }
+}
+
+/// Process spawned by one of the unit `Exec*` commands,
+/// like a `ExecStartPre` step
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Process {
     /// pid
-    pid: u64,
-    /// command line that was executed
-    command: String,
-    /// code
-    code: String,
-    /// status
-    status: String,
+    pub pid: u64,
+    /// command line that was executed, prefixed by its `Exec*` setting,
+    /// like `ExecStartPre=/usr/sbin/sshd -t`
+    pub command: String,
+    /// exit code, like `exited` or `killed`
+    pub code: String,
+    /// exit status when the process `exited`, signal number when it got `killed`.
+    /// `systemctl status` also names it, like `0/SUCCESS`, the name is not kept
+    /// so that processes built from `systemctl show` compare equal.
+    pub status: i32,
 }
 
-impl Default for Process {
-    fn default() -> Process {
-        Process {
-            pid: 0,
-            command: Default::default(),
-            code: Default::default(),
-            status: Default::default(),
+impl FromStr for Process {
+    type Err = Error;
+    /// Builds `Process` from a `systemctl status` process descriptor,
+    /// like `640 ExecStartPre=/usr/sbin/sshd -t (code=exited, status=0/SUCCESS)`
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        let status = status.trim();
+        let (pid, rest) = status.split_once(' ').unwrap_or((status, ""));
+        let pid = pid.parse::<u64>().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid process pid \"{pid}\""),
+            )
+        })?;
+        let mut process = Process {
+            pid,
+            command: rest.trim().to_string(),
+            ..Default::default()
+        };
+        // the command line may itself contain parenthesis
+        if let Some((command, details)) = rest.rsplit_once(" (") {
+            if let Some(details) = details.strip_suffix(')') {
+                process.command = command.trim().to_string();
+                for item in details.split(", ") {
+                    if let Some(code) = item.strip_prefix("code=") {
+                        process.code = code.to_string();
+                    } else if let Some(status) = item.strip_prefix("status=") {
+                        process.status = parse_process_status(status)?;
+                    } else if let Some(signal) = item.strip_prefix("signal=") {
+                        process.status = parse_signal(signal)?;
+                    }
+                }
+            }
         }
+        Ok(process)
+    }
+}
+
+/// Parses a process exit status, numeric like `0` or named like `0/SUCCESS`
+pub(crate) fn parse_process_status(status: &str) -> std::io::Result<i32> {
+    let number = status.split_once('/').map_or(status, |(number, _)| number);
+    number.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid process status \"{status}\""),
+        )
+    })
+}
+
+/// Linux signal numbers, by name as `systemctl status` prints them
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("ILL", 4),
+    ("TRAP", 5),
+    ("ABRT", 6),
+    ("BUS", 7),
+    ("FPE", 8),
+    ("KILL", 9),
+    ("USR1", 10),
+    ("SEGV", 11),
+    ("USR2", 12),
+    ("PIPE", 13),
+    ("ALRM", 14),
+    ("TERM", 15),
+    ("STKFLT", 16),
+    ("CHLD", 17),
+    ("CONT", 18),
+    ("STOP", 19),
+    ("TSTP", 20),
+    ("TTIN", 21),
+    ("TTOU", 22),
+    ("URG", 23),
+    ("XCPU", 24),
+    ("XFSZ", 25),
+    ("VTALRM", 26),
+    ("PROF", 27),
+    ("WINCH", 28),
+    ("IO", 29),
+    ("PWR", 30),
+    ("SYS", 31),
+];
+
+/// Parses the number of a signal that killed a process, like `TERM` or `15`
+pub(crate) fn parse_signal(signal: &str) -> std::io::Result<i32> {
+    if let Ok(number) = signal.parse() {
+        return Ok(number);
     }
-}*/
+    let name = signal.strip_prefix("SIG").unwrap_or(signal);
+    SIGNALS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown process signal \"{signal}\""),
+            )
+        })
+}
 
 /// Doc describes types of documentation possibly
 /// available for a systemd `unit`
@@ -729,12 +1794,22 @@ pub struct Unit {
     pub process: Option<String>,
     /// Optionnal process ID number (main tasklet pid)
     pub pid: Option<u64>,
+    /// Processes spawned by `Exec*` commands, like `ExecStartPre` steps
+    pub processes: Vec<Process>,
+    /// Control group and the processes it contains
+    pub cgroup: Option<ControlGroup>,
     /// Running task(s) infos
     pub tasks: Option<u64>,
     /// Optionnal CPU load consumption infos
     pub cpu: Option<String>,
     /// Optionnal Memory consumption infos
     pub memory: Option<String>,
+    /// Current memory consumption, in bytes
+    pub memory_bytes: Option<u64>,
+    /// Peak memory consumption, in bytes
+    pub memory_peak_bytes: Option<u64>,
+    /// CPU time consumed
+    pub cpu_time: Option<Duration>,
     /// mounted partition (`What`), if this is a `mount`/`automount` unit
     pub mounted: Option<String>,
     /// Mount point (`Where`), if this is a `mount`/`automount` unit
@@ -771,6 +1846,74 @@ mod test {
         SystemCtl::default()
     }
 
+    /// Shell script standing in for systemctl, run with `/bin/sh` as [SystemCtl] path.
+    /// The script and its scratch files are removed once dropped,
+    /// even if the test panicked.
+    pub(crate) struct FakeSystemCtl {
+        name: String,
+        script: PathBuf,
+        files: Vec<PathBuf>,
+    }
+
+    impl FakeSystemCtl {
+        /// Reserves the files of fake systemctl `name`, see [Self::write]
+        pub(crate) fn new(name: &str) -> Self {
+            Self {
+                name: name.to_string(),
+                script: Self::path(name, "sh"),
+                files: Vec::new(),
+            }
+        }
+
+        fn path(name: &str, extension: &str) -> PathBuf {
+            std::env::temp_dir().join(format!(
+                "systemctl-{name}-{}.{extension}",
+                std::process::id()
+            ))
+        }
+
+        /// Returns the path of a scratch file of the script, like a call counter
+        /// or its recorded arguments, removed along with it
+        pub(crate) fn file(&mut self, extension: &str) -> PathBuf {
+            let path = Self::path(&self.name, extension);
+            self.files.push(path.clone());
+            path
+        }
+
+        /// Writes the shell `script`
+        pub(crate) fn write(&self, script: &str) {
+            std::fs::write(&self.script, script).unwrap();
+        }
+
+        /// Returns the script path, first argument of `/bin/sh`
+        pub(crate) fn arg(&self) -> String {
+            self.script.to_string_lossy().to_string()
+        }
+
+        /// Returns a [SystemCtl] running the script
+        pub(crate) fn ctl(&self) -> SystemCtl {
+            SystemCtl::builder()
+                .path("/bin/sh".into())
+                .additional_args(vec![self.arg()])
+                .build()
+        }
+    }
+
+    impl Drop for FakeSystemCtl {
+        fn drop(&mut self) {
+            for path in std::iter::once(&self.script).chain(self.files.iter()) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Returns a [FakeSystemCtl] running `script`
+    pub(crate) fn fake_systemctl(name: &str, script: &str) -> FakeSystemCtl {
+        let fake = FakeSystemCtl::new(name);
+        fake.write(script);
+        fake
+    }
+
     #[test]
     fn test_status_success() {
         let status = ctl().status("cron");
@@ -932,6 +2075,433 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_offline_root() {
+        let root = std::env::temp_dir().join(format!("systemctl-root-{}", std::process::id()));
+        let ctl = SystemCtl::builder()
+            .root(root.to_string_lossy().to_string())
+            .build();
+        assert!(ctl.is_offline());
+        let result = ctl.start("app.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+        let result = ctl.status("app.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+
+        let unit = ServiceUnit::builder()
+            .name("app")
+            .exec_start("/bin/true")
+            .wanted_by(vec!["multi-user.target".into()])
+            .build();
+        let path = ctl.install_unit(&unit, Scope::System).unwrap();
+        assert_eq!(path, root.join("etc/systemd/system/app.service"));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("ExecStart=/bin/true"));
+        assert!(!root.join("etc/systemd/system/app.service.tmp").exists());
+        assert!(ctl.enable("app.service").unwrap().success());
+        // symlinks are relative to the root directory
+        let wants = root.join("etc/systemd/system/multi-user.target.wants/app.service");
+        assert!(wants.symlink_metadata().is_ok());
+        assert!(ctl.mask("other.service").unwrap().success());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_scope() {
+        let ctl = SystemCtl::builder()
+            .scope(Scope::OtherUser("alice".into()))
+            .build();
+        assert_eq!(ctl.scope().args(), ["--user", "--machine=alice@.host"]);
+        assert!(SystemCtl::default().scope().args().is_empty());
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/sh\n";
+        assert_eq!(
+            Scope::home_directory(passwd, "alice"),
+            Some(PathBuf::from("/home/alice"))
+        );
+        assert_eq!(Scope::home_directory(passwd, "bob"), None);
+        assert_eq!(
+            Scope::Global.unit_directory().unwrap(),
+            PathBuf::from("/etc/systemd/user")
+        );
+    }
+
+    #[test]
+    fn test_remote_target() {
+        let fake = fake_systemctl("remote", "echo \"$@\"\n");
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .target(Target::Host {
+                user: Some("admin".into()),
+                host: "server".into(),
+                container: Some("web".into()),
+            })
+            .build();
+        let status = ctl.status("nginx.service").unwrap();
+        assert_eq!(
+            status,
+            "-H admin@server:web --no-pager --plain --full --no-legend status nginx.service\n"
+        );
+        let result = ctl.cat("nginx.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+        let result = ctl
+            .list_drop_ins("nginx", Scope::System)
+            .map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .scope(Scope::User)
+            .target(Target::Machine("container".into()))
+            .build();
+        let status = ctl.status("app.service").unwrap();
+        assert!(status.starts_with("--user -M container --no-pager"));
+
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .root("/mnt".into())
+            .target(Target::Machine("container".into()))
+            .build();
+        let result = ctl.enable("app.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_remote_target_on_path() {
+        // a `systemctl` found on PATH, like the real one
+        let dir = std::env::temp_dir().join(format!("systemctl-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let created = std::process::Command::new("/bin/sh")
+            .arg("-c")
+            .arg("printf '#!/bin/sh\\necho \"$@\"\\n' > \"$1/systemctl\" && chmod 755 \"$1/systemctl\"")
+            .arg("sh")
+            .arg(&dir)
+            .status()
+            .unwrap();
+        assert!(created.success());
+        let path = format!(
+            "{}:{}",
+            dir.display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+        let ctl = |scope: Scope, target: Target| {
+            SystemCtl::builder()
+                .path("systemctl".into())
+                .environment(HashMap::from([("PATH".into(), path.clone())]))
+                .scope(scope)
+                .target(target)
+                .build()
+        };
+        let host = Target::Host {
+            user: None,
+            host: "server".into(),
+            container: None,
+        };
+        let status = ctl(Scope::System, host.clone())
+            .status("nginx.service")
+            .unwrap();
+        assert_eq!(
+            status,
+            "-H server --no-pager --plain --full --no-legend status nginx.service\n"
+        );
+        let status = ctl(Scope::User, Target::Machine("web".into()))
+            .status("app.service")
+            .unwrap();
+        assert!(status.starts_with("--user -M web --no-pager"));
+        for target in [host, Target::Machine("web".into())] {
+            let result = ctl(Scope::OtherUser("alice".into()), target)
+                .status("app.service")
+                .map_err(|e| e.kind());
+            assert_eq!(result, Err(ErrorKind::InvalidInput));
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invocation_environment() {
+        let fake = fake_systemctl(
+            "environment",
+            "echo \"$LC_ALL $SYSTEMD_COLORS [$SYSTEMD_PAGER] $TZ $@\"\n",
+        );
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .environment(HashMap::from([("TZ".to_string(), "UTC".to_string())]))
+            .build();
+        let status = ctl.status("app.service").unwrap();
+        assert_eq!(
+            status,
+            "C 0 [] UTC --no-pager --plain --full --no-legend status app.service\n"
+        );
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .environment(HashMap::from([(
+                "LC_ALL".to_string(),
+                "C.UTF-8".to_string(),
+            )]))
+            .build();
+        let status = ctl.cat("app.service").unwrap();
+        assert!(status.starts_with("C.UTF-8 0 []"));
+    }
+
+    #[test]
+    fn test_list_units_full_from_json() {
+        let raw = r#"[{"unit":"syslog.service","load":"not-found","active":"inactive","sub":"dead","description":"syslog.service"},{"unit":"ssh.service","load":"loaded","active":"active","sub":"running","description":"OpenBSD  Secure Shell ● server"}]"#;
+        let units = SystemCtl::list_units_full_from_json(raw.to_string()).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].unit_name, "syslog.service");
+        assert_eq!(units[0].loaded, LoadedState::NotFound);
+        assert_eq!(units[0].sub_state, "dead");
+        assert_eq!(units[1].active, ActiveState::Active);
+        assert_eq!(units[1].description, "OpenBSD  Secure Shell ● server");
+        assert!(SystemCtl::list_units_full_from_json(String::new())
+            .unwrap()
+            .is_empty());
+        assert!(SystemCtl::list_units_full_from_json("UNIT LOAD".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_list_units_full_from_raw() {
+        let raw = "syslog.service not-found inactive dead syslog.service\n\
+            ssh.service loaded active running OpenBSD Secure Shell server\n";
+        let units = SystemCtl::list_units_full_from_raw(raw.to_string()).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].loaded, LoadedState::NotFound);
+        assert_eq!(units[1].sub_state, "running");
+        assert_eq!(units[1].description, "OpenBSD Secure Shell server");
+        let e = SystemCtl::list_units_full_from_raw("ssh.service loaded active\n".to_string());
+        assert_eq!(e.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_empty_output() {
+        let fake = fake_systemctl(
+            "empty",
+            r#"case "$*" in
+    *--version*) echo "systemd 241" ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        // nothing listed
+        assert!(ctl.list_units_full(None, None, None).unwrap().is_empty());
+        assert!(ctl
+            .list_unit_files_full(None, None, None)
+            .unwrap()
+            .is_empty());
+        // no answer at all
+        let e = ctl.is_active("app.service").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+        let e = ctl.create_unit("app.service").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_list_unit_files_full_from_json() {
+        let raw = r#"[{"unit_file":"dev-hugepages.mount","state":"static","preset":null},{"unit_file":"ssh.service","state":"enabled","preset":"enabled"},{"unit_file":"rsync.service","state":"disabled","preset":"disabled"}]"#;
+        let units = SystemCtl::list_unit_files_full_from_json(raw.to_string()).unwrap();
+        let presets: Vec<_> = units.iter().map(|u| u.vendor_preset).collect();
+        assert_eq!(presets, vec![None, Some(true), Some(false)]);
+        assert_eq!(units[1].unit_file, "ssh.service");
+        assert_eq!(units[2].state, "disabled");
+    }
+
+    #[test]
+    fn test_list_units_full_json_detection() {
+        let fake = fake_systemctl(
+            "json",
+            r#"case "$*" in
+    *--version*) echo "systemd 252 (252.38-1~deb12u1)" ;;
+    *--output=json*) echo '[{"unit":"a.service","load":"loaded","active":"active","sub":"running","description":"A"}]' ;;
+    *) echo "a.service loaded failed failed A" ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        let units = ctl.list_units_full(None, None, None).unwrap();
+        assert_eq!(units[0].active, ActiveState::Active);
+    }
+
+    #[test]
+    fn test_list_dependencies_tree() {
+        let fake = fake_systemctl(
+            "dependencies",
+            r#"case "$*" in
+    *list-dependencies*) printf 'app.target\n  db.service\n  cache.service\n  web.service\n' ;;
+    *--property\ Id,ActiveState,Wants\ *)
+        printf 'Id=app.target\nActiveState=active\nWants=db.service web.service\n\n'
+        printf 'Id=cache.service\nActiveState=inactive\nWants=\n\n'
+        printf 'Id=db.service\nActiveState=active\nWants=\n\n'
+        printf 'Id=web.service\nActiveState=failed\nWants=\n' ;;
+    *) exit 1 ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        let options = DependencyOptions::builder()
+            .kinds(vec![DependencyKind::Wants])
+            .build();
+        let tree = ctl.list_dependencies_tree("app.target", &options).unwrap();
+        assert!(tree.active);
+        assert_eq!(
+            tree.units(),
+            vec!["app.target", "db.service", "web.service"]
+        );
+        assert!(tree.children[0].active);
+        assert!(!tree.children[1].active);
+    }
+
+    #[test]
+    fn test_list_dependencies_tree_chunks() {
+        let fake = fake_systemctl(
+            "dependencies-chunks",
+            &format!(
+                r#"case "$*" in
+    *list-dependencies*) echo app.target; seq -f '  unit%g.service' 300 ;;
+    *show*)
+        # like ARG_MAX, too many units at once fail
+        [ $# -gt {} ] && exit 1
+        for unit in "$@"; do
+            case "$unit" in *.service|*.target) printf 'Id=%s\nActiveState=active\n\n' $unit ;; esac
+        done ;;
+    *) exit 1 ;;
+esac
+"#,
+                SHOW_CHUNK + 10
+            ),
+        );
+        let ctl = fake.ctl();
+        let tree = ctl
+            .list_dependencies_tree("app.target", &DependencyOptions::default())
+            .unwrap();
+        assert_eq!(tree.children.len(), 300);
+        assert!(tree.children.iter().all(|child| child.active));
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let fake = fake_systemctl(
+            "graph",
+            r#"case "$*" in
+    *list-units\ --all*) printf 'app.service loaded active running App\ndb.service loaded active running Db\n' ;;
+    *show\ --property\ Id,Wants,Requires,BindsTo,PartOf,After,Before\ app.service\ db.service)
+        printf 'Id=app.service\nRequires=db.service\nAfter=db.service\n\nId=db.service\n' ;;
+    *) exit 1 ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        let graph = ctl.dependency_graph().unwrap();
+        assert_eq!(
+            graph.topological_order().unwrap(),
+            vec!["db.service", "app.service"]
+        );
+        assert!(graph.is_reachable("app.service", "db.service"));
+    }
+
+    #[test]
+    fn test_create_units() {
+        let mut fake = FakeSystemCtl::new("show-many");
+        let output = fake.file("txt");
+        std::fs::write(
+            &output,
+            [
+                include_str!("../fixtures/show/ssh.txt"),
+                include_str!("../fixtures/show/not-found.txt"),
+                include_str!("../fixtures/show/home-mount.txt"),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        fake.write(&format!(
+            r#"case "$*" in
+    *"show ssh.service nope.service home.mount") cat {} ;;
+    *"cat ssh.service") printf '# /lib/systemd/system/ssh.service\n[Install]\nWantedBy=multi-user.target\nAlso=ssh.socket\n' ;;
+    *"show gone.service") ;;
+    *"show ssh.service") cat {ssh} ;;
+    *) exit 1 ;;
+esac
+"#,
+            output.display(),
+            ssh = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/show/ssh.txt"),
+        ));
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .target(Target::Machine("fleet".into()))
+            .build();
+        let units = ctl.create_units(&["ssh.service", "nope.service", "home.mount"]);
+        assert_eq!(units.len(), 3);
+        assert_eq!(units[0].as_ref().unwrap().name, "ssh");
+        assert_eq!(units[1].as_ref().unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(units[2].as_ref().unwrap().utype, Type::Mount);
+        // no output at all, like without a running service manager
+        let gone = ctl.create_unit("gone.service").map_err(|e| e.kind());
+        assert_eq!(gone.unwrap_err(), ErrorKind::NotFound);
+        // `Also=` is read from the unit file, which remote targets cannot `cat`
+        let local = fake.ctl();
+        let ssh = local.create_unit("ssh.service").unwrap();
+        assert_eq!(ssh.also, Some(vec!["ssh.socket".to_string()]));
+        // mismatching output falls back to one call per unit
+        let units = ctl.create_units(&["ssh.service", "home.mount"]);
+        assert_eq!(units[0].as_ref().unwrap().name, "ssh");
+        assert!(units[1].is_err());
+    }
+
+    #[test]
+    fn test_list_unit_files_full_three_columns() {
+        let raw = include_str!("../fixtures/list-unit-files/systemd-252.txt");
+        let units = SystemCtl::list_unit_files_full_from_raw(raw.to_string(), true).unwrap();
+        assert_eq!(units.len(), 5);
+        assert_eq!(units[0].unit_file, "proc-sys-fs-binfmt_misc.automount");
+        assert_eq!(units[0].vendor_preset, None);
+        assert_eq!(units[2].state, "enabled");
+        assert_eq!(units[2].vendor_preset, Some(true));
+        assert_eq!(units[3].state, "disabled");
+        assert_eq!(units[3].vendor_preset, Some(true));
+        assert_eq!(units[4].vendor_preset, Some(false));
+    }
+
+    #[test]
+    fn test_list_unit_files_full_two_columns() {
+        let raw = include_str!("../fixtures/list-unit-files/systemd-241.txt");
+        let units = SystemCtl::list_unit_files_full_from_raw(raw.to_string(), false).unwrap();
+        assert_eq!(units.len(), 4);
+        assert_eq!(units[2].unit_file, "ssh.service");
+        assert_eq!(units[2].state, "enabled");
+        assert!(units.iter().all(|u| u.vendor_preset.is_none()));
+        let e = SystemCtl::list_unit_files_full_from_raw("ssh.service\n".to_string(), false);
+        assert_eq!(e.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_version_gating() {
+        let fake = fake_systemctl(
+            "version",
+            r#"case "$*" in
+    *--version*) echo "systemd 237"; echo "+PAM -SELINUX" ;;
+    *list-unit-files*) echo "ssh.service enabled" ;;
+    *) echo "$@" ;;
+esac
+"#,
+        );
+        let ctl = fake.ctl();
+        let version = ctl.version().unwrap();
+        assert_eq!(version.version, 237);
+        assert!(version.has_feature("PAM"));
+        assert!(!ctl.supports_json());
+        let result = ctl.freeze("app.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+        let result = ctl.clean("app.service").map_err(|e| e.kind());
+        assert_eq!(result, Err(ErrorKind::Unsupported));
+        let units = ctl.list_unit_files_full(None, None, None).unwrap();
+        assert_eq!(units[0].state, "enabled");
+        assert_eq!(units[0].vendor_preset, None);
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_serde_for_unit() {
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{ActiveState, ServiceProperty, UnitState};
+
+/// Properties a [RestartReport] is built from, for `systemctl show --property`
+pub(crate) fn restart_properties() -> String {
+    [
+        ServiceProperty::Id,
+        ServiceProperty::ActiveState,
+        ServiceProperty::SubState,
+        ServiceProperty::NRestarts,
+        ServiceProperty::InvocationID,
+    ]
+    .map(<&str>::from)
+    .join(",")
+}
+
+/// Number of samples [crate::SystemCtl::restart_verified] takes, evenly spread
+/// over the settle window
+pub(crate) const SETTLE_SAMPLES: u32 = 10;
+
+/// Outcome of [crate::SystemCtl::restart_verified]:
+/// how the unit behaved during the settle window following its restart
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RestartReport {
+    /// Unit name
+    pub unit: String,
+    /// Invocation ID right after the restart
+    pub invocation_id: Option<String>,
+    /// Automatic restart counter right after the restart
+    pub n_restarts: Option<u64>,
+    /// States observed during the settle window, the last one being the final state
+    pub samples: Vec<UnitState>,
+    /// `true` if the unit failed or stopped running during the settle window
+    pub crashed: bool,
+    /// `true` if the unit was restarted during the settle window,
+    /// its invocation ID changed or its restart counter increased
+    pub restarted: bool,
+    /// Error of the caller health check, if it failed during the settle window
+    pub failed_check: Option<String>,
+    /// Error retrieving the unit state, if sampling stopped before the end
+    /// of the settle window
+    pub sampling_error: Option<String>,
+}
+
+/// Health check run by [crate::SystemCtl::restart_verified] at each sample
+/// of the settle window, like probing the service endpoint.
+/// Implemented by closures, which can combine several checks.
+pub trait HealthCheck {
+    /// Returns an error once the unit, currently in `state`, is not healthy
+    fn check(&self, state: &UnitState) -> std::io::Result<()>;
+}
+
+impl<F: Fn(&UnitState) -> std::io::Result<()>> HealthCheck for F {
+    fn check(&self, state: &UnitState) -> std::io::Result<()> {
+        self(state)
+    }
+}
+
+/// Returns a non empty property value
+fn value<'a>(properties: &'a BTreeMap<String, String>, key: &str) -> Option<&'a str> {
+    properties
+        .get(key)
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+impl RestartReport {
+    /// Starts a report from the properties of `unit` right after its restart
+    pub(crate) fn new(unit: &str, properties: &BTreeMap<String, String>) -> Self {
+        Self {
+            unit: unit.to_string(),
+            invocation_id: value(properties, "InvocationID").map(str::to_string),
+            n_restarts: value(properties, "NRestarts").and_then(|n| n.parse().ok()),
+            ..Default::default()
+        }
+    }
+
+    /// Records a sample of the unit properties.
+    /// Returns `false` once the unit crashed or restarted, as more samples are pointless.
+    pub(crate) fn record(&mut self, properties: &BTreeMap<String, String>) -> bool {
+        let state = UnitState::from_properties(properties);
+        // a crashed unit waits in `activating (auto-restart)` until restarted
+        if !matches!(
+            state.active,
+            ActiveState::Active | ActiveState::Activating | ActiveState::Reloading
+        ) || state.sub_state == "auto-restart"
+        {
+            self.crashed = true;
+        }
+        let invocation_id = value(properties, "InvocationID");
+        if self.invocation_id.is_some() && invocation_id != self.invocation_id.as_deref() {
+            self.restarted = true;
+        }
+        let n_restarts: Option<u64> = value(properties, "NRestarts").and_then(|n| n.parse().ok());
+        if n_restarts > self.n_restarts {
+            self.restarted = true;
+        }
+        self.samples.push(state);
+        !self.crashed && !self.restarted
+    }
+
+    /// Returns the last observed state
+    pub fn state(&self) -> Option<&UnitState> {
+        self.samples.last()
+    }
+
+    /// Records the outcome of the caller health check on the last sample.
+    /// Returns `false` if it failed, as more samples are pointless.
+    pub(crate) fn record_check<C: HealthCheck>(&mut self, check: &C) -> bool {
+        let Some(state) = self.samples.last() else {
+            return true;
+        };
+        match check.check(state) {
+            Ok(()) => true,
+            Err(e) => {
+                self.failed_check = Some(e.to_string());
+                false
+            },
+        }
+    }
+
+    /// Returns `true` if the unit stayed up and passed the health check
+    /// during the whole settle window
+    pub fn is_healthy(&self) -> bool {
+        !self.crashed
+            && !self.restarted
+            && self.failed_check.is_none()
+            && self.sampling_error.is_none()
+            && self
+                .state()
+                .is_some_and(|state| state.active == ActiveState::Active)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::Error;
+    use std::time::Duration;
+
+    use crate::test::FakeSystemCtl;
+
+    fn properties(state: &str, n_restarts: &str, invocation_id: &str) -> BTreeMap<String, String> {
+        [
+            ("ActiveState", state),
+            ("SubState", "running"),
+            ("NRestarts", n_restarts),
+            ("InvocationID", invocation_id),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = RestartReport::new("app.service", &properties("activating", "0", "a1"));
+        assert_eq!(report.n_restarts, Some(0));
+        assert!(!report.is_healthy());
+        assert!(report.record(&properties("active", "0", "a1")));
+        assert!(report.is_healthy());
+
+        let mut restarted = report.clone();
+        assert!(!restarted.record(&properties("active", "1", "b2")));
+        assert!(restarted.restarted);
+        assert!(!restarted.crashed);
+        assert!(!restarted.is_healthy());
+
+        let mut crashed = report.clone();
+        assert!(!crashed.record(&properties("failed", "0", "a1")));
+        assert!(crashed.crashed);
+        assert_eq!(crashed.state().map(|s| s.active), Some(ActiveState::Failed));
+
+        let mut unhealthy = report.clone();
+        assert!(unhealthy.record_check(&|_: &UnitState| Ok(())));
+        assert!(!unhealthy.record_check(&|_: &UnitState| Err(std::io::Error::other("HTTP 503"))));
+        assert_eq!(unhealthy.failed_check.as_deref(), Some("HTTP 503"));
+        assert!(!unhealthy.is_healthy());
+    }
+
+    #[test]
+    fn test_restart_verified() {
+        let mut fake = FakeSystemCtl::new("restart");
+        let counter = fake.file("count");
+        let vanishing = fake.file("vanishing");
+        fake.write(&format!(
+            r#"case "$*" in
+    restart*) exit 0 ;;
+    *show*stable.service) printf 'Id=stable.service\nActiveState=active\nSubState=running\nNRestarts=0\nInvocationID=a1\n' ;;
+    *show*flaky.service)
+        echo >> {counter}
+        if [ "$(wc -l < {counter})" -lt 3 ]; then
+            printf 'Id=flaky.service\nActiveState=active\nSubState=running\nNRestarts=0\nInvocationID=a1\n'
+        else
+            printf 'Id=flaky.service\nActiveState=activating\nSubState=auto-restart\nNRestarts=1\nInvocationID=b2\n'
+        fi ;;
+    *show*vanishing.service)
+        echo >> {vanishing}
+        [ "$(wc -l < {vanishing})" -lt 4 ] || exit 5
+        printf 'Id=vanishing.service\nActiveState=active\nSubState=running\nNRestarts=0\nInvocationID=c3\n' ;;
+    *) exit 5 ;;
+esac
+"#,
+            counter = counter.display(),
+            vanishing = vanishing.display()
+        ));
+        let ctl = fake.ctl();
+        let settle = Duration::from_millis(40);
+        let healthy = |_: &UnitState| Ok(());
+        let report = ctl
+            .restart_verified("stable.service", settle, healthy)
+            .unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.samples.len(), SETTLE_SAMPLES as usize);
+        assert_eq!(report.invocation_id.as_deref(), Some("a1"));
+
+        // the unit stays up, but the service does not answer
+        let probes = std::cell::Cell::new(0);
+        let probe = |state: &UnitState| {
+            assert_eq!(state.active, ActiveState::Active);
+            probes.set(probes.get() + 1);
+            if probes.get() < 2 {
+                Ok(())
+            } else {
+                Err(Error::other("connection refused"))
+            }
+        };
+        let report = ctl
+            .restart_verified("stable.service", settle, probe)
+            .unwrap();
+        assert!(!report.is_healthy());
+        assert!(!report.crashed);
+        assert_eq!(report.failed_check.as_deref(), Some("connection refused"));
+        assert_eq!(report.samples.len(), 2);
+
+        // samples taken before the failure are kept
+        let report = ctl
+            .restart_verified("vanishing.service", settle, healthy)
+            .unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(report.samples.len(), 2);
+        assert!(report.sampling_error.is_some());
+
+        let mut rolled_back = false;
+        let report = ctl
+            .restart_verified_or_rollback("flaky.service", settle, healthy, |report| {
+                rolled_back = true;
+                assert!(report.crashed);
+                Ok(())
+            })
+            .unwrap();
+        assert!(rolled_back);
+        assert!(report.restarted);
+        assert_eq!(report.samples.len(), 2);
+    }
+}
@@ -0,0 +1,250 @@
+use bon::Builder;
+use std::fmt;
+use std::time::Duration;
+use strum_macros::{EnumString, IntoStaticStr};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::UnitFile;
+
+/// `Restart=` policy of a service
+#[derive(Copy, Clone, PartialEq, Eq, EnumString, IntoStaticStr, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Restart {
+    #[strum(serialize = "no")]
+    #[default]
+    No,
+    #[strum(serialize = "on-success")]
+    OnSuccess,
+    #[strum(serialize = "on-failure")]
+    OnFailure,
+    #[strum(serialize = "on-abnormal")]
+    OnAbnormal,
+    #[strum(serialize = "on-watchdog")]
+    OnWatchdog,
+    #[strum(serialize = "on-abort")]
+    OnAbort,
+    #[strum(serialize = "always")]
+    Always,
+}
+
+/// `Type=` of a service, describing how its startup is notified
+#[derive(Copy, Clone, PartialEq, Eq, EnumString, IntoStaticStr, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ServiceType {
+    #[strum(serialize = "simple")]
+    #[default]
+    Simple,
+    #[strum(serialize = "exec")]
+    Exec,
+    #[strum(serialize = "forking")]
+    Forking,
+    #[strum(serialize = "oneshot")]
+    Oneshot,
+    #[strum(serialize = "dbus")]
+    Dbus,
+    #[strum(serialize = "notify")]
+    Notify,
+    #[strum(serialize = "notify-reload")]
+    NotifyReload,
+    #[strum(serialize = "idle")]
+    Idle,
+}
+
+/// Typed description of a `.service` unit file.
+///
+/// ```
+/// use systemctl::{Restart, ServiceUnit};
+/// let unit = ServiceUnit::builder()
+///     .name("my-app")
+///     .description("My application")
+///     .exec_start("/usr/bin/my-app --serve")
+///     .restart(Restart::OnFailure)
+///     .wanted_by(vec!["multi-user.target".into()])
+///     .build();
+/// assert_eq!(unit.file_name(), "my-app.service");
+/// assert!(unit.to_string().contains("Restart=on-failure"));
+/// ```
+#[derive(Builder, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServiceUnit {
+    /// Unit name, the `.service` suffix is optional
+    #[builder(into)]
+    pub name: String,
+    /// `Description=`
+    #[builder(into)]
+    pub description: Option<String>,
+    /// `Documentation=` URIs
+    #[builder(default)]
+    pub documentation: Vec<String>,
+    /// `Wants=` units
+    #[builder(default)]
+    pub wants: Vec<String>,
+    /// `Requires=` units
+    #[builder(default)]
+    pub requires: Vec<String>,
+    /// `After=` units
+    #[builder(default)]
+    pub after: Vec<String>,
+    /// `Before=` units
+    #[builder(default)]
+    pub before: Vec<String>,
+    /// Service `Type=`
+    pub service_type: Option<ServiceType>,
+    /// `ExecStartPre=` command lines
+    #[builder(default)]
+    pub exec_start_pre: Vec<String>,
+    /// `ExecStart=` command line
+    #[builder(into)]
+    pub exec_start: String,
+    /// `ExecReload=` command line
+    #[builder(into)]
+    pub exec_reload: Option<String>,
+    /// `ExecStop=` command line
+    #[builder(into)]
+    pub exec_stop: Option<String>,
+    /// `Restart=` policy
+    pub restart: Option<Restart>,
+    /// `RestartSec=` delay
+    pub restart_sec: Option<Duration>,
+    /// `User=` to run as
+    #[builder(into)]
+    pub user: Option<String>,
+    /// `Group=` to run as
+    #[builder(into)]
+    pub group: Option<String>,
+    /// `WorkingDirectory=`
+    #[builder(into)]
+    pub working_directory: Option<String>,
+    /// `Environment=` variables, as `(name, value)` pairs
+    #[builder(default)]
+    pub environment: Vec<(String, String)>,
+    /// `WantedBy=` units, used by `systemctl enable`
+    #[builder(default)]
+    pub wanted_by: Vec<String>,
+    /// `RequiredBy=` units, used by `systemctl enable`
+    #[builder(default)]
+    pub required_by: Vec<String>,
+}
+
+impl ServiceUnit {
+    /// Returns the unit file name, `name.service`
+    pub fn file_name(&self) -> String {
+        if self.name.ends_with(".service") {
+            self.name.clone()
+        } else {
+            format!("{}.service", self.name)
+        }
+    }
+
+    /// Renders self as a [UnitFile]
+    pub fn to_unit_file(&self) -> UnitFile {
+        let mut unit = UnitFile::default();
+        if let Some(description) = &self.description {
+            unit.append("Unit", "Description", description);
+        }
+        let lists = [
+            ("Documentation", &self.documentation),
+            ("Wants", &self.wants),
+            ("Requires", &self.requires),
+            ("After", &self.after),
+            ("Before", &self.before),
+        ];
+        for (key, values) in lists {
+            if !values.is_empty() {
+                unit.append("Unit", key, &values.join(" "));
+            }
+        }
+        if let Some(service_type) = self.service_type {
+            unit.append("Service", "Type", service_type.into());
+        }
+        for command in &self.exec_start_pre {
+            unit.append("Service", "ExecStartPre", command);
+        }
+        unit.append("Service", "ExecStart", &self.exec_start);
+        let values = [
+            ("ExecReload", self.exec_reload.as_deref()),
+            ("ExecStop", self.exec_stop.as_deref()),
+            ("Restart", self.restart.map(Into::into)),
+        ];
+        for (key, value) in values {
+            if let Some(value) = value {
+                unit.append("Service", key, value);
+            }
+        }
+        if let Some(delay) = self.restart_sec {
+            unit.append("Service", "RestartSec", &format!("{}ms", delay.as_millis()));
+        }
+        let values = [
+            ("User", &self.user),
+            ("Group", &self.group),
+            ("WorkingDirectory", &self.working_directory),
+        ];
+        for (key, value) in values {
+            if let Some(value) = value {
+                unit.append("Service", key, value);
+            }
+        }
+        for (name, value) in &self.environment {
+            unit.append("Service", "Environment", &format!("\"{name}={value}\""));
+        }
+        if !self.wanted_by.is_empty() {
+            unit.append("Install", "WantedBy", &self.wanted_by.join(" "));
+        }
+        if !self.required_by.is_empty() {
+            unit.append("Install", "RequiredBy", &self.required_by.join(" "));
+        }
+        unit
+    }
+}
+
+impl fmt::Display for ServiceUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_unit_file())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let unit = ServiceUnit::builder()
+            .name("app.service")
+            .description("Application")
+            .after(vec!["network-online.target".into()])
+            .wants(vec!["network-online.target".into()])
+            .service_type(ServiceType::Notify)
+            .exec_start("/usr/bin/app")
+            .restart(Restart::OnFailure)
+            .restart_sec(Duration::from_secs(5))
+            .user("app")
+            .environment(vec![("RUST_LOG".into(), "info".into())])
+            .wanted_by(vec!["multi-user.target".into()])
+            .build();
+        assert_eq!(unit.file_name(), "app.service");
+        assert_eq!(
+            unit.to_string(),
+            "[Unit]
+Description=Application
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=notify
+ExecStart=/usr/bin/app
+Restart=on-failure
+RestartSec=5000ms
+User=app
+Environment=\"RUST_LOG=info\"
+
+[Install]
+WantedBy=multi-user.target
+"
+        );
+        let parsed = UnitFile::parse(None, &unit.to_string());
+        assert_eq!(parsed.get("Service", "Restart"), Some("on-failure"));
+    }
+}
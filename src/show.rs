@@ -0,0 +1,427 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::{
+    parse_process_status, AutoStartStatus, ControlGroup, Doc, LoadedState, Process, Type, Unit,
+};
+
+/// Splits `systemctl show` output into one property map per unit.
+/// Several units are separated by a blank line.
+pub(crate) fn parse_properties(output: &str) -> Vec<BTreeMap<String, String>> {
+    let mut units = Vec::new();
+    let mut properties = BTreeMap::new();
+    for line in output.lines() {
+        if line.is_empty() {
+            if !properties.is_empty() {
+                units.push(std::mem::take(&mut properties));
+            }
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            // repeated properties, like `ExecReload`, are kept on separate lines
+            properties
+                .entry(key.to_string())
+                .and_modify(|previous: &mut String| {
+                    previous.push('\n');
+                    previous.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+    }
+    if !properties.is_empty() {
+        units.push(properties);
+    }
+    units
+}
+
+/// Returns the property value, `None` when empty or not set
+fn value<'a>(properties: &'a BTreeMap<String, String>, key: &str) -> Option<&'a str> {
+    properties
+        .get(key)
+        .map(String::as_str)
+        .filter(|value| !value.is_empty() && *value != "[not set]")
+}
+
+/// Returns a numeric property, `None` when not set or infinite
+fn number(properties: &BTreeMap<String, String>, key: &str) -> Option<u64> {
+    value(properties, key)
+        .and_then(|value| value.parse::<u64>().ok())
+        .filter(|value| *value != u64::MAX)
+}
+
+/// Returns a space separated list property
+fn list(properties: &BTreeMap<String, String>, key: &str) -> Option<Vec<String>> {
+    value(properties, key).map(|value| value.split_ascii_whitespace().map(str::to_string).collect())
+}
+
+/// Returns the command lines of an `Exec*` property,
+/// `{ path=/usr/sbin/sshd ; argv[]=/usr/sbin/sshd -D ; ... }`
+fn commands(properties: &BTreeMap<String, String>, key: &str) -> Vec<String> {
+    let Some(value) = value(properties, key) else {
+        return Vec::new();
+    };
+    value
+        .lines()
+        .filter_map(|line| {
+            let (_, argv) = line.split_once("argv[]=")?;
+            let end = argv.find(" ; ").unwrap_or(argv.len());
+            Some(argv[..end].trim().to_string())
+        })
+        .collect()
+}
+
+/// `Exec*` settings, in the order systemd runs them
+const EXEC_PROPERTIES: [&str; 7] = [
+    "ExecCondition",
+    "ExecStartPre",
+    "ExecStart",
+    "ExecStartPost",
+    "ExecReload",
+    "ExecStop",
+    "ExecStopPost",
+];
+
+/// Returns the processes that `Exec*` commands last spawned and that have exited,
+/// `{ ... ; argv[]=/usr/sbin/sshd -t ; ... ; pid=640 ; code=exited ; status=0 }`
+fn processes(properties: &BTreeMap<String, String>) -> Vec<Process> {
+    let mut processes = Vec::new();
+    for key in EXEC_PROPERTIES {
+        let Some(value) = value(properties, key) else {
+            continue;
+        };
+        for line in value.lines() {
+            let line = line.trim().trim_start_matches('{').trim_end_matches('}');
+            let mut process = Process::default();
+            for field in line.split(" ; ") {
+                match field.trim().split_once('=') {
+                    Some(("argv[]", argv)) => process.command = format!("{key}={argv}"),
+                    Some(("pid", pid)) => process.pid = pid.parse().unwrap_or_default(),
+                    Some(("code", code)) => process.code = code.to_string(),
+                    Some(("status", status)) => {
+                        process.status = parse_process_status(status).unwrap_or_default()
+                    },
+                    _ => {},
+                }
+            }
+            // never started, or still running
+            if process.pid != 0 && process.code != "(null)" {
+                processes.push(process);
+            }
+        }
+    }
+    processes
+}
+
+/// Formats `bytes` the way `systemctl status` does, like `5.2M`
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 6] = [
+        ("E", 1 << 60),
+        ("P", 1 << 50),
+        ("T", 1 << 40),
+        ("G", 1 << 30),
+        ("M", 1 << 20),
+        ("K", 1 << 10),
+    ];
+    for (suffix, factor) in UNITS {
+        if bytes >= factor {
+            let tenths = (bytes as u128 * 10 / factor as u128) as u64;
+            return format!("{}.{}{suffix}", tenths / 10, tenths % 10);
+        }
+    }
+    format!("{bytes}B")
+}
+
+/// Formats `nsec` the way `systemctl status` does, like `1.204s`
+pub(crate) fn format_nsec(nsec: u64) -> String {
+    let msec = nsec / 1_000_000;
+    if msec < 1000 {
+        return format!("{msec}ms");
+    }
+    let (minutes, msec) = (msec / 60_000, msec % 60_000);
+    let seconds = match msec % 1000 {
+        0 => format!("{}s", msec / 1000),
+        frac => format!("{}.{frac:03}s", msec / 1000),
+    };
+    if minutes > 0 {
+        format!("{minutes}min {seconds}")
+    } else {
+        seconds
+    }
+}
+
+/// Parses a size formatted by systemd, like `5.3M` or `512B`
+pub(crate) fn parse_bytes(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, suffix) = size.split_at(split);
+    let factor: u64 = match suffix.trim() {
+        "" | "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        "P" => 1 << 50,
+        "E" => 1 << 60,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Some((number * factor as f64).round() as u64)
+}
+
+/// Parses a time span formatted by systemd, like `1min 5.432s` or `12ms`
+pub(crate) fn parse_duration(span: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut parsed = false;
+    for item in span.split_ascii_whitespace() {
+        let split = item.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, suffix) = item.split_at(split);
+        let seconds = match suffix {
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "min" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            "w" => 604800.0,
+            "M" => 2629800.0,
+            "y" => 31557600.0,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * seconds;
+        parsed = true;
+    }
+    parsed.then(|| Duration::from_secs_f64(total))
+}
+
+impl Unit {
+    /// Builds a `Unit` from `systemctl show $unit` output
+    pub fn from_show(output: &str) -> std::io::Result<Self> {
+        let properties = parse_properties(output)
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "empty show output"))?;
+        Self::from_properties(&properties)
+    }
+
+    /// Builds a `Unit` from the properties of a single unit
+    pub(crate) fn from_properties(properties: &BTreeMap<String, String>) -> std::io::Result<Self> {
+        let id = value(properties, "Id")
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "missing Id property"))?;
+        let (name, utype) = id.rsplit_once('.').ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, format!("unit \"{id}\" has no type"))
+        })?;
+        let utype = Type::from_str(utype).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unit \"{id}\" has an unknown type"),
+            )
+        })?;
+        let docs: Option<Vec<Doc>> = value(properties, "Documentation").map(|docs| {
+            docs.split_ascii_whitespace()
+                .filter_map(|doc| Doc::from_str(doc.trim_matches('"')).ok())
+                .collect()
+        });
+        let script = value(properties, "SourcePath")
+            .or_else(|| value(properties, "FragmentPath"))
+            .unwrap_or_default();
+        Ok(Unit {
+            name: name.to_string(),
+            utype,
+            description: value(properties, "Description").map(str::to_string),
+            loaded_state: value(properties, "LoadState")
+                .and_then(|state| LoadedState::from_str(state).ok())
+                .unwrap_or(LoadedState::Unknown),
+            auto_start: value(properties, "UnitFileState")
+                .and_then(|state| AutoStartStatus::from_str(state).ok())
+                .unwrap_or_default(),
+            active: value(properties, "ActiveState") == Some("active"),
+            preset: value(properties, "UnitFilePreset") == Some("enabled"),
+            script: script.to_string(),
+            restart_policy: value(properties, "Restart").map(str::to_string),
+            kill_mode: value(properties, "KillMode").map(str::to_string),
+            process: None,
+            pid: number(properties, "MainPID").filter(|pid| *pid != 0),
+            processes: processes(properties),
+            cgroup: value(properties, "ControlGroup").map(|path| ControlGroup {
+                path: path.to_string(),
+                ..Default::default()
+            }),
+            tasks: number(properties, "TasksCurrent"),
+            cpu: number(properties, "CPUUsageNSec").map(format_nsec),
+            memory: number(properties, "MemoryCurrent").map(format_bytes),
+            memory_bytes: number(properties, "MemoryCurrent"),
+            memory_peak_bytes: number(properties, "MemoryPeak"),
+            cpu_time: number(properties, "CPUUsageNSec").map(Duration::from_nanos),
+            mounted: value(properties, "What").map(str::to_string),
+            mountpoint: value(properties, "Where").map(str::to_string),
+            docs: docs.filter(|docs| !docs.is_empty()),
+            wants: list(properties, "Wants"),
+            wanted_by: list(properties, "WantedBy"),
+            // `Also=` has no property, see [crate::SystemCtl::create_unit]
+            also: None,
+            before: list(properties, "Before"),
+            after: list(properties, "After"),
+            // the last command, like the last assignment of the unit file
+            exec_start: commands(properties, "ExecStart").pop(),
+            exec_reload: commands(properties, "ExecReload").pop(),
+            transient: value(properties, "Transient") == Some("yes"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_show() {
+        let u = Unit::from_show(include_str!("../fixtures/show/ssh.txt")).unwrap();
+        assert_eq!(u.name, "ssh");
+        assert_eq!(u.utype, Type::Service);
+        assert_eq!(
+            u.description.as_deref(),
+            Some("OpenBSD Secure Shell server")
+        );
+        assert_eq!(u.loaded_state, LoadedState::Loaded);
+        assert_eq!(u.auto_start, AutoStartStatus::Enabled);
+        assert!(u.active);
+        assert!(u.preset);
+        assert_eq!(u.script, "/lib/systemd/system/ssh.service");
+        assert_eq!(u.restart_policy.as_deref(), Some("on-failure"));
+        assert_eq!(u.kill_mode.as_deref(), Some("process"));
+        assert_eq!(u.pid, Some(702));
+        assert_eq!(u.tasks, Some(1));
+        assert_eq!(u.memory.as_deref(), Some("5.2M"));
+        assert_eq!(u.cpu.as_deref(), Some("1.204s"));
+        assert_eq!(u.memory_bytes, Some(5554176));
+        assert_eq!(u.memory_peak_bytes, Some(7340032));
+        assert_eq!(u.cpu_time, Some(Duration::from_millis(1204)));
+        assert_eq!(
+            u.docs,
+            Some(vec![
+                Doc::Man("sshd".into()),
+                Doc::Man("sshd_config".into())
+            ])
+        );
+        assert_eq!(u.wants, Some(vec!["network.target".to_string()]));
+        assert_eq!(u.wanted_by, Some(vec!["multi-user.target".to_string()]));
+        assert_eq!(u.after.map(|after| after.len()), Some(7));
+        assert_eq!(
+            u.exec_start.as_deref(),
+            Some("/usr/sbin/sshd -D $SSHD_OPTS")
+        );
+        assert_eq!(u.exec_reload.as_deref(), Some("/bin/kill -HUP $MAINPID"));
+        assert_eq!(
+            u.processes,
+            vec![Process {
+                pid: 640,
+                command: "ExecStartPre=/usr/sbin/sshd -t".into(),
+                code: "exited".into(),
+                status: 0,
+            }]
+        );
+        // same process, as listed by `systemctl status`
+        let status_process: Process =
+            "640 ExecStartPre=/usr/sbin/sshd -t (code=exited, status=0/SUCCESS)"
+                .parse()
+                .unwrap();
+        assert_eq!(u.processes[0], status_process);
+        assert!(!u.transient);
+        assert_eq!(
+            u.cgroup.map(|cgroup| cgroup.path),
+            Some("/system.slice/ssh.service".to_string())
+        );
+    }
+
+    #[test]
+    fn test_killed_process() {
+        let show = Unit::from_show(include_str!("../fixtures/show/worker-killed.txt")).unwrap();
+        let status =
+            Unit::from_status_output(include_str!("../fixtures/status/worker-killed.txt")).unwrap();
+        assert_eq!(
+            show.processes[1],
+            Process {
+                pid: 812,
+                command: "ExecStart=/usr/bin/worker --queue jobs".into(),
+                code: "killed".into(),
+                status: 9,
+            }
+        );
+        assert_eq!(show.processes, status.processes);
+        assert_eq!(status.pid, Some(812));
+        assert_eq!(status.process, None);
+        let process: Process = "77 ExecStopPost=/bin/cleanup (code=dumped, signal=SEGV)"
+            .parse()
+            .unwrap();
+        assert_eq!(process.status, 11);
+        assert!("77 ExecStopPost=/bin/cleanup (code=killed, signal=NOPE)"
+            .parse::<Process>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_show_mount() {
+        let u = Unit::from_show(include_str!("../fixtures/show/home-mount.txt")).unwrap();
+        assert_eq!(u.utype, Type::Mount);
+        assert_eq!(u.script, "/etc/fstab");
+        assert_eq!(u.auto_start, AutoStartStatus::Generated);
+        assert!(!u.preset);
+        assert_eq!(u.mounted.as_deref(), Some("/dev/sda3"));
+        assert_eq!(u.mountpoint.as_deref(), Some("/home"));
+        assert_eq!(u.tasks, Some(0));
+        assert_eq!(u.memory.as_deref(), Some("24.0K"));
+        assert_eq!(u.cpu.as_deref(), Some("3ms"));
+    }
+
+    #[test]
+    fn test_from_show_not_found() {
+        let u = Unit::from_show(include_str!("../fixtures/show/not-found.txt")).unwrap();
+        assert_eq!(u.loaded_state, LoadedState::NotFound);
+        assert_eq!(u.pid, None);
+        assert_eq!(u.tasks, None);
+        assert_eq!(u.memory, None);
+        assert_eq!(u.memory_bytes, None);
+        assert_eq!(u.cpu_time, None);
+        assert!(Unit::from_show("").is_err());
+        assert!(Unit::from_show("Description=x\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let units = parse_properties("Id=a.service\nNames=a.service\n\nId=b.service\n");
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1].get("Id").map(String::as_str), Some("b.service"));
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(3 << 30), "3.0G");
+        assert_eq!(format_nsec(65_432_000_000), "1min 5.432s");
+        assert_eq!(format_nsec(2_000_000_000), "2s");
+    }
+
+    #[test]
+    fn test_parse_human() {
+        assert_eq!(parse_bytes("512B"), Some(512));
+        assert_eq!(parse_bytes("24.0K"), Some(24576));
+        assert_eq!(parse_bytes("1.5M"), Some(1572864));
+        assert_eq!(parse_bytes("3G"), Some(3 << 30));
+        assert_eq!(parse_bytes("5.2X"), None);
+        assert_eq!(parse_bytes(&format_bytes(3 << 30)), Some(3 << 30));
+        assert_eq!(parse_duration("12ms"), Some(Duration::from_millis(12)));
+        assert_eq!(
+            parse_duration("1min 5.432s"),
+            Some(Duration::from_millis(65432))
+        );
+        assert_eq!(parse_duration("2h 3min"), Some(Duration::from_secs(7380)));
+        assert_eq!(parse_duration("500us"), Some(Duration::from_micros(500)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("soon"), None);
+    }
+}
@@ -0,0 +1,326 @@
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+use crate::show::{parse_bytes, parse_duration};
+use crate::{AutoStartStatus, ControlGroup, Doc, LoadedState, Process, Type, Unit};
+
+/// Builds a parse error pointing at `line` (1-based index `n`) of the status output
+fn parse_error(n: usize, line: &str, message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("status line {n}: {message}: \"{}\"", line.trim()),
+    )
+}
+
+/// Parses the `(pid, process name)` of lines like `Main PID: 787 (gpm)`
+fn parse_pid(n: usize, line: &str, value: &str) -> std::io::Result<(u64, Option<String>)> {
+    let (pid, rest) = value.split_once(' ').unwrap_or((value, ""));
+    let pid = pid
+        .parse::<u64>()
+        .map_err(|_| parse_error(n, line, "invalid pid"))?;
+    let rest = rest.trim();
+    let rest = rest
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(rest);
+    // exited processes are described like `(code=exited, status=203/EXEC)`
+    let process = (!rest.is_empty() && !rest.starts_with("code=")).then(|| rest.to_string());
+    Ok((pid, process))
+}
+
+impl Unit {
+    /// Builds a `Unit` from `systemctl status $unit` output.
+    ///
+    /// Lines that are not understood are ignored,
+    /// but malformed values of known lines are reported
+    /// as `ErrorKind::InvalidData` errors, with line context.
+    pub fn from_status_output(status: &str) -> std::io::Result<Self> {
+        let mut u = Unit::default();
+        let mut lines = status.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let (n, first) = lines
+            .find(|(_, line)| !line.trim().is_empty())
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "empty status output"))?;
+
+        // "● name.type - description", the state glyph is optional
+        let mut items = first.split_ascii_whitespace().peekable();
+        if items
+            .peek()
+            .is_some_and(|item| !item.chars().any(char::is_alphanumeric))
+        {
+            items.next();
+        }
+        let name_raw = items
+            .next()
+            .ok_or_else(|| parse_error(n, first, "missing unit name"))?;
+        if let Some(delim) = items.next() {
+            if delim == "-" {
+                // --> description string is provided
+                let items: Vec<_> = items.collect();
+                u.description = Some(itertools::join(&items, " "));
+            }
+        }
+        let (name, utype_raw) = name_raw
+            .rsplit_once('.')
+            .ok_or_else(|| parse_error(n, first, "unit name has no type"))?;
+        // `type` is deduced from .extension
+        u.utype =
+            Type::from_str(utype_raw).map_err(|_| parse_error(n, first, "unknown unit type"))?;
+        u.name = name.to_string();
+
+        let mut is_doc = false;
+        let mut cgroup: Option<(&str, Vec<&str>)> = None;
+        for (n, raw) in lines {
+            let line = raw.trim_start();
+            if line.is_empty() {
+                // journal excerpt follows
+                break;
+            }
+            if let Some((_, tree)) = cgroup.as_mut() {
+                // unicode or ASCII tree glyphs, depending on the locale
+                if line.starts_with(['├', '└', '│', '|', '`']) {
+                    tree.push(raw);
+                    continue;
+                }
+            }
+            if let Some(value) = line.strip_prefix("Loaded: ") {
+                let (state, details) = match value.split_once(' ') {
+                    Some((state, details)) => (state, Some(details.trim())),
+                    None => (value.trim(), None),
+                };
+                u.loaded_state = LoadedState::from_str(state).unwrap_or(LoadedState::Unknown);
+                if let Some(details) = details {
+                    let details = details
+                        .strip_prefix('(')
+                        .and_then(|details| details.strip_suffix(')'))
+                        .ok_or_else(|| parse_error(n, raw, "unbalanced parenthesis"))?;
+                    if u.loaded_state == LoadedState::Loaded {
+                        // (script; auto start; preset)
+                        let items: Vec<&str> = details.split(';').map(str::trim).collect();
+                        u.script = items[0].to_string();
+                        if let Some(auto_start) = items.get(1) {
+                            u.auto_start = AutoStartStatus::from_str(auto_start)
+                                .unwrap_or(AutoStartStatus::Disabled);
+                        }
+                        if let Some(preset) = items.get(2) {
+                            u.preset = preset.ends_with("enabled");
+                        }
+                    }
+                }
+            } else if let Some(line) = line.strip_prefix("Transient: ") {
+                u.transient = line == "yes";
+            } else if let Some(line) = line.strip_prefix("Active: ") {
+                u.active = line.starts_with("active ");
+            } else if let Some(line) = line.strip_prefix("Docs: ") {
+                is_doc = true;
+                if let Ok(doc) = Doc::from_str(line) {
+                    u.docs.get_or_insert_with(Vec::new).push(doc);
+                }
+                continue;
+            } else if let Some(line) = line.strip_prefix("What: ") {
+                // mountpoint infos
+                u.mounted = Some(line.to_string())
+            } else if let Some(line) = line.strip_prefix("Where: ") {
+                // mountpoint infos
+                u.mountpoint = Some(line.to_string());
+            } else if let Some(value) = line
+                .strip_prefix("Main PID: ")
+                .or_else(|| line.strip_prefix("Cntrl PID: "))
+            {
+                // example -> Main PID: 787 (gpm)
+                let (pid, process) = parse_pid(n, raw, value)?;
+                u.pid = Some(pid);
+                u.process = process;
+            } else if let Some(value) = line.strip_prefix("Process: ") {
+                // example -> Process: 640 ExecStartPre=/usr/sbin/sshd -t (code=exited, status=0/SUCCESS)
+                let process =
+                    Process::from_str(value).map_err(|_| parse_error(n, raw, "invalid process"))?;
+                u.processes.push(process);
+            } else if let Some(path) = line.strip_prefix("CGroup: ") {
+                cgroup = Some((path.trim(), Vec::new()));
+            } else if let Some(line) = line.strip_prefix("Tasks: ") {
+                // example -> Tasks: 1 (limit: 4915)
+                let tasks = line.split_ascii_whitespace().next().unwrap_or_default();
+                u.tasks = Some(
+                    tasks
+                        .parse()
+                        .map_err(|_| parse_error(n, raw, "invalid tasks count"))?,
+                );
+            } else if let Some(line) = line.strip_prefix("Memory: ") {
+                // example -> Memory: 6.1M (peak: 7.0M)
+                let line = line.trim();
+                u.memory = Some(line.to_string());
+                let (current, details) = line.split_once(' ').unwrap_or((line, ""));
+                u.memory_bytes = parse_bytes(current);
+                u.memory_peak_bytes = details
+                    .trim_start_matches('(')
+                    .trim_end_matches(')')
+                    .split(", ")
+                    .find_map(|item| item.strip_prefix("peak: "))
+                    .and_then(parse_bytes);
+            } else if let Some(line) = line.strip_prefix("CPU: ") {
+                u.cpu = Some(line.trim().to_string());
+                u.cpu_time = parse_duration(line);
+            } else if is_doc && raw.starts_with(' ') && !line.contains(": ") {
+                // multi line docs
+                if let Ok(doc) = Doc::from_str(line) {
+                    u.docs.get_or_insert_with(Vec::new).push(doc);
+                }
+                continue;
+            }
+            is_doc = false;
+        }
+        u.cgroup = cgroup.map(|(path, tree)| ControlGroup::from_status_tree(path, &tree));
+        Ok(u)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(fixture: &str) -> Unit {
+        Unit::from_status_output(fixture).unwrap()
+    }
+
+    #[test]
+    fn test_ssh() {
+        let u = parse(include_str!("../fixtures/status/ssh.txt"));
+        assert_eq!(u.name, "ssh");
+        assert_eq!(u.utype, Type::Service);
+        assert_eq!(
+            u.description.as_deref(),
+            Some("OpenBSD Secure Shell server")
+        );
+        assert_eq!(u.loaded_state, LoadedState::Loaded);
+        assert_eq!(u.script, "/lib/systemd/system/ssh.service");
+        assert_eq!(u.auto_start, AutoStartStatus::Enabled);
+        assert!(u.preset);
+        assert!(u.active);
+        assert_eq!(
+            u.docs,
+            Some(vec![
+                Doc::Man("sshd".into()),
+                Doc::Man("sshd_config".into())
+            ])
+        );
+        assert_eq!(u.pid, Some(702));
+        assert_eq!(u.process.as_deref(), Some("sshd"));
+        assert_eq!(
+            u.processes,
+            vec![Process {
+                pid: 640,
+                command: "ExecStartPre=/usr/sbin/sshd -t".into(),
+                code: "exited".into(),
+                status: 0,
+            }]
+        );
+        assert_eq!(u.memory.as_deref(), Some("5.3M"));
+        assert_eq!(u.cpu.as_deref(), Some("1.204s"));
+        assert_eq!(u.tasks, Some(1));
+        assert_eq!(u.memory_bytes, Some(5557453));
+        assert_eq!(u.memory_peak_bytes, None);
+        assert_eq!(u.cpu_time, Some(std::time::Duration::from_millis(1204)));
+        assert_eq!(
+            u.cgroup,
+            Some(ControlGroup {
+                path: "/system.slice/ssh.service".into(),
+                processes: vec![(
+                    702,
+                    "sshd: /usr/sbin/sshd -D [listener] 0 of 10-100 startups".into()
+                )],
+                children: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_legacy_layout() {
+        let u = parse(include_str!("../fixtures/status/cron-241.txt"));
+        assert_eq!(u.name, "cron");
+        assert!(u.preset);
+        assert!(u.active);
+        assert_eq!(u.docs, Some(vec![Doc::Man("cron".into())]));
+        assert_eq!(u.pid, Some(456));
+    }
+
+    #[test]
+    fn test_failed() {
+        let u = parse(include_str!("../fixtures/status/nginx-failed.txt"));
+        assert_eq!(u.name, "nginx");
+        assert!(!u.active);
+        assert_eq!(u.pid, None);
+        assert_eq!(u.processes.len(), 1);
+        assert_eq!(
+            u.processes[0].command,
+            "ExecStartPre=/usr/sbin/nginx -t -q -g daemon on; master_process on;"
+        );
+        assert_eq!(u.processes[0].status, 1);
+        assert_eq!(u.cpu.as_deref(), Some("12ms"));
+    }
+
+    #[test]
+    fn test_not_loaded() {
+        let u = parse(include_str!("../fixtures/status/not-found.txt"));
+        assert_eq!(u.name, "syslog");
+        assert_eq!(u.description, None);
+        assert_eq!(u.loaded_state, LoadedState::NotFound);
+        let u = parse(include_str!("../fixtures/status/bad-setting.txt"));
+        assert_eq!(u.loaded_state, LoadedState::BadSetting);
+        assert_eq!(u.pid, Some(4242));
+        assert_eq!(u.process, None);
+        let u = parse(include_str!("../fixtures/status/masked.txt"));
+        assert_eq!(u.loaded_state, LoadedState::Masked);
+        assert_eq!(u.script, "");
+    }
+
+    #[test]
+    fn test_other_types() {
+        let u = parse(include_str!("../fixtures/status/home-mount.txt"));
+        assert_eq!(u.utype, Type::Mount);
+        assert_eq!(u.auto_start, AutoStartStatus::Generated);
+        assert_eq!(u.mounted.as_deref(), Some("/dev/sda3"));
+        assert_eq!(u.mountpoint.as_deref(), Some("/home"));
+        assert_eq!(u.docs.map(|docs| docs.len()), Some(2));
+        let u = parse(include_str!("../fixtures/status/sda-device.txt"));
+        assert_eq!(u.utype, Type::Device);
+        assert_eq!(u.loaded_state, LoadedState::Loaded);
+        assert_eq!(u.script, "");
+        let u = parse(include_str!("../fixtures/status/transient.txt"));
+        assert!(u.transient);
+        assert_eq!(u.auto_start, AutoStartStatus::Transient);
+        let u = parse(include_str!("../fixtures/status/timer.txt"));
+        assert_eq!(u.utype, Type::Timer);
+        assert_eq!(u.cgroup, None);
+        let u = parse(include_str!("../fixtures/status/user-manager.txt"));
+        assert_eq!(u.name, "user@1000");
+        assert_eq!(u.pid, Some(1101));
+        assert_eq!(u.tasks, Some(4));
+        assert_eq!(u.memory.as_deref(), Some("6.1M (peak: 7.0M)"));
+        assert_eq!(u.memory_bytes, parse_bytes("6.1M"));
+        assert_eq!(u.memory_peak_bytes, Some(7340032));
+        let cgroup = u.cgroup.unwrap();
+        assert_eq!(cgroup.children.len(), 2);
+        assert_eq!(cgroup.all_processes().len(), 3);
+        // LC_ALL=C output, as run by this crate
+        let ascii = parse(include_str!("../fixtures/status/user-manager-ascii.txt"));
+        assert_eq!(ascii.cgroup, Some(cgroup));
+    }
+
+    #[test]
+    fn test_errors() {
+        let kind = |status: &str| Unit::from_status_output(status).map_err(|e| e.kind());
+        assert_eq!(kind(""), Err(ErrorKind::UnexpectedEof));
+        assert_eq!(kind("● noextension"), Err(ErrorKind::InvalidData));
+        assert_eq!(kind("● foo.unknown - Foo"), Err(ErrorKind::InvalidData));
+        let e = Unit::from_status_output("● foo.service\n   Main PID: abc (foo)").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "status line 2: invalid pid: \"Main PID: abc (foo)\""
+        );
+        let e =
+            Unit::from_status_output("● foo.service\n  Loaded: loaded (/x; enabled").unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("status line 2: unbalanced parenthesis"));
+    }
+}
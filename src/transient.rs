@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+
+use bon::Builder;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ServiceProperty;
+
+/// Default `systemd-run` binary
+pub(crate) const SYSTEMD_RUN_PATH: &str = "/usr/bin/systemd-run";
+
+/// Command to run as a transient unit with `systemd-run`,
+/// see [crate::SystemCtl::run_transient].
+///
+/// ```
+/// use systemctl::{ServiceProperty, TransientUnit};
+/// let job = TransientUnit::builder()
+///     .command(vec!["/usr/bin/backup".into(), "--full".into()])
+///     .unit("backup")
+///     .properties(vec![(ServiceProperty::MemoryMax, "512M".into())])
+///     .collect(true)
+///     .build();
+/// assert_eq!(
+///     job.args().unwrap(),
+///     vec!["--unit=backup", "--property=MemoryMax=512M", "--collect", "--", "/usr/bin/backup", "--full"]
+/// );
+/// ```
+#[derive(Builder, Clone, Debug, Default, PartialEq)]
+pub struct TransientUnit {
+    /// Command line to run, program first
+    pub command: Vec<String>,
+    /// Unit name (`--unit`), generated by systemd when missing
+    #[builder(into)]
+    pub unit: Option<String>,
+    /// Unit properties (`--property`), like resource limits.
+    /// They are passed with their name as is, most of them match the unit file settings.
+    #[builder(default)]
+    pub properties: Vec<(ServiceProperty, String)>,
+    /// Runs the command as a `.scope` unit rather than a `.service` (`--scope`):
+    /// `systemd-run` then runs it itself, with the environment of the calling process,
+    /// and returns once it exited
+    #[builder(default)]
+    pub scope: bool,
+    /// Slice to place the unit in (`--slice`)
+    #[builder(into)]
+    pub slice: Option<String>,
+    /// User name or ID to run the command as (`--uid`)
+    #[builder(into)]
+    pub uid: Option<String>,
+    /// Working directory of the command (`--working-directory`)
+    #[builder(into)]
+    pub working_directory: Option<String>,
+    /// Environment variables of the command (`--setenv`)
+    #[builder(default)]
+    pub environment: BTreeMap<String, String>,
+    /// Waits for the service to terminate (`--wait`).
+    /// Its failure is then returned as an error.
+    #[builder(default)]
+    pub wait: bool,
+    /// Connects the command output to `systemd-run` (`--pipe`),
+    /// see [TransientRun::stdout]. Implies waiting for it to terminate.
+    #[builder(default)]
+    pub pipe: bool,
+    /// Unloads the unit once terminated, even if it failed (`--collect`)
+    #[builder(default)]
+    pub collect: bool,
+    /// The path to the systemd-run binary, by default it's `/usr/bin/systemd-run`
+    path: Option<String>,
+    /// Allows passing extra arguments to systemd-run
+    #[builder(default)]
+    additional_args: Vec<String>,
+}
+
+impl TransientUnit {
+    /// Returns the `systemd-run` arguments running this unit
+    pub fn args(&self) -> std::io::Result<Vec<String>> {
+        if self.command.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "A transient unit needs a command",
+            ));
+        }
+        if self.scope && (self.wait || self.pipe) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "--wait and --pipe do not apply to scope units",
+            ));
+        }
+        let mut args = Vec::new();
+        let options = [
+            ("--unit", self.unit.as_deref()),
+            ("--slice", self.slice.as_deref()),
+            ("--uid", self.uid.as_deref()),
+            ("--working-directory", self.working_directory.as_deref()),
+        ];
+        for (option, value) in options {
+            if let Some(value) = value {
+                args.push(format!("{option}={value}"));
+            }
+        }
+        for (key, value) in self.environment.iter() {
+            args.push(format!("--setenv={key}={value}"));
+        }
+        for (property, value) in self.properties.iter() {
+            args.push(format!("--property={}={value}", <&str>::from(property)));
+        }
+        let flags = [
+            ("--scope", self.scope),
+            ("--wait", self.wait),
+            ("--pipe", self.pipe),
+            ("--collect", self.collect),
+        ];
+        for (flag, set) in flags {
+            if set {
+                args.push(flag.to_string());
+            }
+        }
+        args.push("--".to_string());
+        args.extend(self.command.iter().cloned());
+        Ok(args)
+    }
+
+    /// Returns the path to the systemd-run binary
+    pub(crate) fn get_path(&self) -> &str {
+        self.path.as_deref().unwrap_or(SYSTEMD_RUN_PATH)
+    }
+
+    /// Returns the extra systemd-run arguments
+    pub(crate) fn additional_args(&self) -> &[String] {
+        &self.additional_args
+    }
+
+    /// Returns the full unit name, as given by `--unit`
+    pub(crate) fn unit_name(&self) -> Option<String> {
+        let unit = self.unit.as_deref()?;
+        let suffix = if self.scope { ".scope" } else { ".service" };
+        Some(if unit.ends_with(suffix) {
+            unit.to_string()
+        } else {
+            format!("{unit}{suffix}")
+        })
+    }
+}
+
+/// Transient unit launched by [crate::SystemCtl::run_transient].
+///
+/// While it is loaded, [crate::SystemCtl::create_unit] returns it
+/// with [crate::Unit::transient] set.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TransientRun {
+    /// Unit name, like `run-u42.service`
+    pub unit: String,
+    /// Invocation ID of the unit, when it could be retrieved
+    pub invocation_id: Option<String>,
+    /// Command output, with [TransientUnit::pipe] or [TransientUnit::scope]
+    /// as the command then writes to the output of `systemd-run`
+    pub stdout: String,
+}
+
+impl TransientRun {
+    /// Parses the unit name and invocation ID `systemd-run` reports on stderr, like
+    /// `Running as unit: run-u42.service; invocation ID: 5d3c...`
+    pub(crate) fn from_stderr(stderr: &str) -> Option<Self> {
+        let line = stderr.lines().find_map(|line| {
+            line.trim()
+                .strip_prefix("Running as unit: ")
+                .or_else(|| line.trim().strip_prefix("Running scope as unit: "))
+        })?;
+        let (unit, invocation_id) = match line.split_once(';') {
+            Some((unit, rest)) => (
+                unit,
+                rest.trim()
+                    .strip_prefix("invocation ID: ")
+                    .map(|id| id.trim().to_string()),
+            ),
+            None => (line, None),
+        };
+        Some(Self {
+            unit: unit.trim().to_string(),
+            invocation_id,
+            stdout: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use crate::test::{fake_systemctl, FakeSystemCtl};
+    use crate::{Scope, SystemCtl};
+
+    #[test]
+    fn test_args() {
+        let unit = TransientUnit::builder()
+            .command(vec!["/bin/true".into()])
+            .slice("batch.slice")
+            .uid("nobody")
+            .working_directory("/tmp")
+            .environment(BTreeMap::from([("MODE".into(), "full".into())]))
+            .properties(vec![
+                (ServiceProperty::MemoryMax, "1G".into()),
+                (ServiceProperty::CPUWeight, "20".into()),
+            ])
+            .wait(true)
+            .pipe(true)
+            .build();
+        assert_eq!(
+            unit.args().unwrap(),
+            vec![
+                "--slice=batch.slice",
+                "--uid=nobody",
+                "--working-directory=/tmp",
+                "--setenv=MODE=full",
+                "--property=MemoryMax=1G",
+                "--property=CPUWeight=20",
+                "--wait",
+                "--pipe",
+                "--",
+                "/bin/true"
+            ]
+        );
+        assert_eq!(unit.unit_name(), None);
+
+        let scope = TransientUnit::builder()
+            .command(vec!["/bin/true".into()])
+            .unit("job")
+            .scope(true)
+            .build();
+        assert_eq!(scope.unit_name().as_deref(), Some("job.scope"));
+        let invalid = TransientUnit {
+            wait: true,
+            ..scope.clone()
+        };
+        assert_eq!(invalid.args().unwrap_err().kind(), ErrorKind::InvalidInput);
+        let empty = TransientUnit::default();
+        assert_eq!(empty.args().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_from_stderr() {
+        let run = TransientRun::from_stderr(
+            "Running as unit: run-u42.service; invocation ID: 5d3c0e1f2a3b4c5d\n",
+        )
+        .unwrap();
+        assert_eq!(run.unit, "run-u42.service");
+        assert_eq!(run.invocation_id.as_deref(), Some("5d3c0e1f2a3b4c5d"));
+        let run = TransientRun::from_stderr("Running scope as unit: run-r7.scope\n").unwrap();
+        assert_eq!(run.unit, "run-r7.scope");
+        assert_eq!(run.invocation_id, None);
+        assert_eq!(
+            TransientRun::from_stderr("Failed to start transient service unit"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_transient() {
+        let mut run = FakeSystemCtl::new("run");
+        let args = run.file("args");
+        run.write(&format!(
+            r#"printf '%s\n' "$@" > {args}
+case "$*" in
+    *--pipe*) echo 'Running as unit: run-u7.service; invocation ID: 0a1b' >&2; echo done ;;
+    *--scope*) echo 'Running scope as unit: run-r1.scope' >&2; echo "${{SYSTEMD_COLORS-unset}} ${{RUN_TEST-unset}}" ;;
+    *--unit=fail*) echo 'Job failed' >&2; exit 1 ;;
+    *) echo 'Running as unit: run-u42.service' >&2 ;;
+esac
+"#,
+            args = args.display()
+        ));
+        let fake = fake_systemctl(
+            "run-show",
+            "case \"$*\" in\n    *InvocationID*run-u42.service) echo 5d3c ;;\n    *) exit 5 ;;\nesac\n",
+        );
+        let ctl = SystemCtl::builder()
+            .path("/bin/sh".into())
+            .additional_args(vec![fake.arg()])
+            .scope(Scope::User)
+            .environment(HashMap::from([("RUN_TEST".into(), "1".into())]))
+            .build();
+        let job = |unit: Option<&str>, pipe: bool| {
+            TransientUnit::builder()
+                .command(vec!["/usr/bin/backup".into()])
+                .maybe_unit(unit)
+                .pipe(pipe)
+                .properties(vec![(ServiceProperty::MemoryMax, "512M".into())])
+                .path("/bin/sh".into())
+                .additional_args(vec![run.arg()])
+                .build()
+        };
+
+        let started = ctl.run_transient(&job(None, false)).unwrap();
+        assert_eq!(started.unit, "run-u42.service");
+        assert_eq!(started.invocation_id.as_deref(), Some("5d3c"));
+        let recorded = std::fs::read_to_string(&args).unwrap();
+        assert_eq!(
+            recorded.lines().collect::<Vec<_>>(),
+            vec![
+                "--user",
+                "--property=MemoryMax=512M",
+                "--",
+                "/usr/bin/backup"
+            ]
+        );
+
+        let piped = ctl.run_transient(&job(None, true)).unwrap();
+        assert_eq!(piped.unit, "run-u7.service");
+        assert_eq!(piped.invocation_id.as_deref(), Some("0a1b"));
+        assert_eq!(piped.stdout, "done\n");
+
+        // scope commands do not inherit the systemctl environment
+        let mut scope = job(None, false);
+        scope.scope = true;
+        let scope = ctl.run_transient(&scope).unwrap();
+        assert_eq!(scope.unit, "run-r1.scope");
+        assert_eq!(scope.stdout, "unset unset\n");
+
+        let failed = ctl.run_transient(&job(Some("fail"), false)).unwrap_err();
+        assert!(failed.to_string().contains("Job failed"));
+
+        let offline = SystemCtl::builder().root("/mnt".into()).build();
+        let unsupported = offline.run_transient(&job(None, false)).unwrap_err();
+        assert_eq!(unsupported.kind(), ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,504 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// One line (or continued logical line) of a unit file.
+/// Every variant keeps its raw text, so rendering is lossless.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum UnitFileLine {
+    /// Empty line, possibly holding whitespaces
+    Blank(String),
+    /// Comment line, starting with `#` or `;`
+    Comment(String),
+    /// `[Section]` header
+    Section {
+        /// Section name, without brackets
+        name: String,
+        /// Raw line
+        raw: String,
+    },
+    /// `Key=Value` assignment, possibly spanning several
+    /// lines terminated by a `\`
+    Entry {
+        /// Assignment key
+        key: String,
+        /// Assignment value, continuation lines joined by a single space.
+        /// An empty value resets list settings like `ExecStart=`.
+        value: String,
+        /// Raw text, including continuation lines
+        raw: String,
+    },
+    /// Any other line, kept verbatim
+    Other(String),
+}
+
+impl UnitFileLine {
+    /// Returns the raw text of this line
+    pub fn raw(&self) -> &str {
+        match self {
+            Self::Blank(raw) | Self::Comment(raw) | Self::Other(raw) => raw,
+            Self::Section { raw, .. } | Self::Entry { raw, .. } => raw,
+        }
+    }
+}
+
+/// A single file contributing to a unit configuration:
+/// the main fragment or one of its drop-ins
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnitFileFragment {
+    /// Source file this fragment was read from, as reported
+    /// by the `# /path` header of `systemctl cat`
+    pub path: Option<String>,
+    /// Ordered lines of this fragment
+    pub lines: Vec<UnitFileLine>,
+}
+
+impl UnitFileFragment {
+    /// Returns `true` if this fragment is a drop-in (`<unit>.d/*.conf`)
+    pub fn is_drop_in(&self) -> bool {
+        self.path
+            .as_deref()
+            .and_then(|path| path.rsplit_once('/'))
+            .is_some_and(|(dir, file)| dir.ends_with(".d") && file.ends_with(".conf"))
+    }
+
+    /// Returns the ordered `(section, key, value)` assignments of this fragment
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        let mut section = "";
+        self.lines.iter().filter_map(move |line| match line {
+            UnitFileLine::Section { name, .. } => {
+                section = name;
+                None
+            },
+            UnitFileLine::Entry { key, value, .. } => Some((section, key.as_str(), value.as_str())),
+            _ => None,
+        })
+    }
+
+    fn parse(path: Option<String>, content: &[&str]) -> Self {
+        let mut lines = Vec::new();
+        // (raw, key, value) of an assignment being continued
+        let mut continued: Option<(String, String, String)> = None;
+        for line in content.iter().copied() {
+            if let Some((mut raw, key, mut value)) = continued.take() {
+                raw.push('\n');
+                raw.push_str(line);
+                let trimmed = line.trim();
+                // comments inside continued lines are ignored by systemd
+                if !trimmed.starts_with('#') && !trimmed.starts_with(';') {
+                    let part = trimmed.strip_suffix('\\').unwrap_or(trimmed).trim();
+                    if !part.is_empty() {
+                        if !value.is_empty() {
+                            value.push(' ');
+                        }
+                        value.push_str(part);
+                    }
+                }
+                if trimmed.ends_with('\\') {
+                    continued = Some((raw, key, value));
+                } else {
+                    lines.push(UnitFileLine::Entry { key, value, raw });
+                }
+                continue;
+            }
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                lines.push(UnitFileLine::Blank(line.to_string()));
+            } else if trimmed.starts_with('#') || trimmed.starts_with(';') {
+                lines.push(UnitFileLine::Comment(line.to_string()));
+            } else if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                lines.push(UnitFileLine::Section {
+                    name: name.to_string(),
+                    raw: line.to_string(),
+                });
+            } else if let Some((key, value)) = trimmed.split_once('=') {
+                let key = key.trim().to_string();
+                match value.trim_end().strip_suffix('\\') {
+                    Some(value) => {
+                        continued = Some((line.to_string(), key, value.trim().to_string()));
+                    },
+                    None => lines.push(UnitFileLine::Entry {
+                        key,
+                        value: value.trim().to_string(),
+                        raw: line.to_string(),
+                    }),
+                }
+            } else {
+                lines.push(UnitFileLine::Other(line.to_string()));
+            }
+        }
+        if let Some((raw, key, value)) = continued {
+            lines.push(UnitFileLine::Entry { key, value, raw });
+        }
+        Self { path, lines }
+    }
+}
+
+/// A parsed unit file, made of its main fragment and drop-ins.
+///
+/// Parsing preserves comments, blank lines, continuation lines and
+/// fragment headers, so `to_string()` renders the original text back.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnitFile {
+    /// Fragments, in the order systemd applies them
+    pub fragments: Vec<UnitFileFragment>,
+    /// `true` if the content ends with a line break
+    pub trailing_newline: bool,
+}
+
+impl UnitFile {
+    /// Parses a single unit file or drop-in content, read from `path`.
+    /// Rendering starts with a `# path` header when `path` is set.
+    pub fn parse(path: Option<&str>, content: &str) -> Self {
+        let (content, trailing_newline) = match content.strip_suffix('\n') {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+        Self {
+            fragments: vec![UnitFileFragment::parse(
+                path.map(str::to_string),
+                &content.split('\n').collect::<Vec<_>>(),
+            )],
+            trailing_newline,
+        }
+    }
+
+    /// Parses `systemctl cat` output, splitting fragments
+    /// on their `# /path/to/file` headers
+    pub fn from_cat(content: &str) -> Self {
+        let (content, trailing_newline) = match content.strip_suffix('\n') {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+        let mut fragments = Vec::new();
+        let mut path: Option<String> = None;
+        let mut body: Vec<&str> = Vec::new();
+        let mut started = false;
+        for line in content.split('\n') {
+            // headers come first or after the blank line ending the previous file
+            let boundary = body.last().is_none_or(|last| last.trim().is_empty());
+            if let Some(header) = Self::cat_header(line).filter(|_| boundary) {
+                if started {
+                    fragments.push(UnitFileFragment::parse(path.take(), &body));
+                    body.clear();
+                }
+                path = Some(header.to_string());
+                started = true;
+            } else {
+                body.push(line);
+                started = true;
+            }
+        }
+        if started {
+            fragments.push(UnitFileFragment::parse(path, &body));
+        }
+        Self {
+            fragments,
+            trailing_newline,
+        }
+    }
+
+    /// Returns the path of a `# /path/to/file` header line of `systemctl cat`
+    fn cat_header(line: &str) -> Option<&str> {
+        let path = line.strip_prefix("# ")?;
+        (path.starts_with('/') && !path.contains(char::is_whitespace)).then_some(path)
+    }
+
+    /// Returns the main fragment (the unit file itself), if any
+    pub fn fragment(&self) -> Option<&UnitFileFragment> {
+        self.fragments.iter().find(|f| !f.is_drop_in())
+    }
+
+    /// Returns the drop-in fragments
+    pub fn drop_ins(&self) -> impl Iterator<Item = &UnitFileFragment> {
+        self.fragments.iter().filter(|f| f.is_drop_in())
+    }
+
+    /// Returns the ordered list of section names, without duplicates
+    pub fn sections(&self) -> Vec<&str> {
+        let mut sections: Vec<&str> = Vec::new();
+        for line in self.fragments.iter().flat_map(|f| f.lines.iter()) {
+            if let UnitFileLine::Section { name, .. } = line {
+                if !sections.contains(&name.as_str()) {
+                    sections.push(name);
+                }
+            }
+        }
+        sections
+    }
+
+    /// Returns every `(key, value, source path)` assignment of given `section`,
+    /// in order, across all fragments. Resets are kept as empty values.
+    pub fn entries<'a>(
+        &'a self,
+        section: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a str, Option<&'a str>)> + 'a {
+        self.fragments.iter().flat_map(move |fragment| {
+            fragment
+                .entries()
+                .filter(move |(s, _, _)| *s == section)
+                .map(move |(_, k, v)| (k, v, fragment.path.as_deref()))
+        })
+    }
+
+    /// Returns the effective values of `key` in `section`,
+    /// where an empty assignment resets previous values
+    pub fn get_all(&self, section: &str, key: &str) -> Vec<&str> {
+        let mut values = Vec::new();
+        let assignments = self
+            .fragments
+            .iter()
+            .flat_map(UnitFileFragment::entries)
+            .filter(|(s, k, _)| *s == section && *k == key);
+        for (_, _, value) in assignments {
+            if value.is_empty() {
+                values.clear();
+            } else {
+                values.push(value);
+            }
+        }
+        values
+    }
+
+    /// Returns the effective (last) value of `key` in `section`
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.get_all(section, key).pop()
+    }
+
+    /// Appends a `key=value` assignment to `section` of the last fragment,
+    /// creating the section when it does not exist yet
+    pub fn append(&mut self, section: &str, key: &str, value: &str) {
+        if self.fragments.is_empty() {
+            self.fragments.push(UnitFileFragment::default());
+        }
+        let fragment = self.fragments.last_mut().unwrap();
+        let entry = UnitFileLine::Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+            raw: format!("{key}={value}"),
+        };
+        // insert after the last non blank line of the section
+        let mut position = None;
+        let mut in_section = false;
+        for (i, line) in fragment.lines.iter().enumerate() {
+            match line {
+                UnitFileLine::Section { name, .. } => {
+                    in_section = name == section;
+                    if in_section {
+                        position = Some(i + 1);
+                    }
+                },
+                UnitFileLine::Blank(_) => {},
+                _ if in_section => position = Some(i + 1),
+                _ => {},
+            }
+        }
+        match position {
+            Some(i) => fragment.lines.insert(i, entry),
+            None => {
+                if fragment
+                    .lines
+                    .last()
+                    .is_some_and(|l| !matches!(l, UnitFileLine::Blank(_)))
+                {
+                    fragment.lines.push(UnitFileLine::Blank(String::new()));
+                }
+                fragment.lines.push(UnitFileLine::Section {
+                    name: section.to_string(),
+                    raw: format!("[{section}]"),
+                });
+                fragment.lines.push(entry);
+            },
+        }
+        self.trailing_newline = true;
+    }
+
+    /// Replaces all assignments of `key` in `section` with a single `key=value`
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        self.remove(section, key);
+        self.append(section, key, value);
+    }
+
+    /// Removes all assignments of `key` in `section`
+    pub fn remove(&mut self, section: &str, key: &str) {
+        for fragment in self.fragments.iter_mut() {
+            let mut current = String::new();
+            fragment.lines.retain(|line| match line {
+                UnitFileLine::Section { name, .. } => {
+                    current.clone_from(name);
+                    true
+                },
+                UnitFileLine::Entry { key: k, .. } => !(current == section && k == key),
+                _ => true,
+            });
+        }
+    }
+}
+
+impl FromStr for UnitFile {
+    type Err = std::convert::Infallible;
+    /// Parses a single unit file content, see [UnitFile::parse]
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(None, content))
+    }
+}
+
+impl fmt::Display for UnitFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for fragment in &self.fragments {
+            if let Some(path) = &fragment.path {
+                if !first {
+                    writeln!(f)?;
+                }
+                write!(f, "# {path}")?;
+                first = false;
+            }
+            for line in &fragment.lines {
+                if !first {
+                    writeln!(f)?;
+                }
+                write!(f, "{}", line.raw())?;
+                first = false;
+            }
+        }
+        if self.trailing_newline && !first {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CAT: &str = "# /lib/systemd/system/ssh.service
+[Unit]
+Description=OpenBSD Secure Shell server
+Documentation=man:sshd(8) man:sshd_config(5)
+After=network.target auditd.service
+ConditionPathExists=!/etc/ssh/sshd_not_to_be_run
+
+[Service]
+EnvironmentFile=-/etc/default/ssh
+ExecStartPre=/usr/sbin/sshd -t
+ExecStart=/usr/sbin/sshd -D $SSHD_OPTS
+ExecReload=/usr/sbin/sshd -t
+ExecReload=/bin/kill -HUP $MAINPID
+KillMode=process
+Restart=on-failure
+
+[Install]
+WantedBy=multi-user.target
+Alias=sshd.service
+
+# /etc/systemd/system/ssh.service.d/override.conf
+[Service]
+; reset vendor command
+ExecStart=
+ExecStart=/usr/sbin/sshd -D \\
+    -o LogLevel=VERBOSE \\
+    $SSHD_OPTS
+Restart=always
+";
+
+    #[test]
+    fn test_round_trip() {
+        let unit = UnitFile::from_cat(CAT);
+        assert_eq!(unit.to_string(), CAT);
+        let unit = UnitFile::parse(None, CAT);
+        assert_eq!(unit.to_string(), CAT);
+        let unit: UnitFile = "[Unit]\nDescription=x".parse().unwrap();
+        assert_eq!(unit.to_string(), "[Unit]\nDescription=x");
+    }
+
+    #[test]
+    fn test_fragments() {
+        let unit = UnitFile::from_cat(CAT);
+        assert_eq!(unit.fragments.len(), 2);
+        assert_eq!(
+            unit.fragment().and_then(|f| f.path.as_deref()),
+            Some("/lib/systemd/system/ssh.service")
+        );
+        let drop_ins: Vec<_> = unit.drop_ins().collect();
+        assert_eq!(drop_ins.len(), 1);
+        assert_eq!(
+            drop_ins[0].path.as_deref(),
+            Some("/etc/systemd/system/ssh.service.d/override.conf")
+        );
+        assert_eq!(unit.sections(), vec!["Unit", "Service", "Install"]);
+    }
+
+    #[test]
+    fn test_cat_comments() {
+        let cat = "# /etc/systemd/system/app.service\n\
+            [Service]\n\
+            # /etc/default/app is sourced too\n\
+            # /etc/default/app\n\
+            EnvironmentFile=/etc/default/app\n\
+            \n\
+            # /etc/systemd/system/app.service.d/verbose.conf\n\
+            [Service]\n\
+            ExecStart=/usr/bin/app \\  \n\
+            \t--verbose\n";
+        let unit = UnitFile::from_cat(cat);
+        assert_eq!(unit.to_string(), cat);
+        assert_eq!(unit.fragments.len(), 2);
+        assert_eq!(
+            unit.get("Service", "EnvironmentFile"),
+            Some("/etc/default/app")
+        );
+        assert_eq!(
+            unit.fragments[1].path.as_deref(),
+            Some("/etc/systemd/system/app.service.d/verbose.conf")
+        );
+        assert_eq!(
+            unit.get("Service", "ExecStart"),
+            Some("/usr/bin/app --verbose")
+        );
+    }
+
+    #[test]
+    fn test_effective_values() {
+        let unit = UnitFile::from_cat(CAT);
+        assert_eq!(
+            unit.get_all("Service", "ExecStart"),
+            vec!["/usr/sbin/sshd -D -o LogLevel=VERBOSE $SSHD_OPTS"]
+        );
+        assert_eq!(unit.get_all("Service", "ExecReload").len(), 2);
+        assert_eq!(unit.get("Service", "Restart"), Some("always"));
+        assert_eq!(unit.get("Install", "WantedBy"), Some("multi-user.target"));
+        assert_eq!(unit.get("Unit", "Restart"), None);
+        let sources: Vec<_> = unit
+            .entries("Service")
+            .filter(|(k, _, _)| *k == "Restart")
+            .map(|(_, _, path)| path.unwrap())
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                "/lib/systemd/system/ssh.service",
+                "/etc/systemd/system/ssh.service.d/override.conf"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edition() {
+        let mut unit = UnitFile::default();
+        unit.append("Unit", "Description", "Test");
+        unit.append("Service", "ExecStart", "/bin/true");
+        unit.append("Unit", "After", "network.target");
+        unit.set("Service", "ExecStart", "/bin/false");
+        assert_eq!(
+            unit.to_string(),
+            "[Unit]\nDescription=Test\nAfter=network.target\n\n[Service]\nExecStart=/bin/false\n"
+        );
+    }
+}
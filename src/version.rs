@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// systemd version and compile time features,
+/// as reported by `systemctl --version`
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SystemdVersion {
+    /// Version number, like `252`
+    pub version: u32,
+    /// Full version string, like `252.38-1~deb12u1`
+    pub full: String,
+    /// Compile time features: `+PAM` is reported as `("PAM", true)`,
+    /// `-SELINUX` as `("SELINUX", false)`
+    pub features: BTreeMap<String, bool>,
+    /// Compile time settings, like `default-hierarchy=unified`
+    pub settings: BTreeMap<String, String>,
+}
+
+impl SystemdVersion {
+    /// Returns `true` if systemd was compiled with given `feature`, like `SELINUX`
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.get(feature).copied().unwrap_or(false)
+    }
+
+    /// Returns `true` if `list-unit-files` reports the vendor preset column (systemd 245)
+    pub fn has_vendor_preset_column(&self) -> bool {
+        self.version >= 245
+    }
+
+    /// Returns `true` if listings support `--output=json` (systemd 251)
+    pub fn supports_json_output(&self) -> bool {
+        self.version >= 251
+    }
+
+    /// Returns `true` if units can be frozen and thawed (systemd 246)
+    pub fn supports_freeze(&self) -> bool {
+        self.version >= 246
+    }
+
+    /// Returns `true` if unit resources can be cleaned (systemd 243)
+    pub fn supports_clean(&self) -> bool {
+        self.version >= 243
+    }
+}
+
+impl FromStr for SystemdVersion {
+    type Err = Error;
+    /// Parses `systemctl --version` output
+    fn from_str(output: &str) -> Result<Self, Self::Err> {
+        let mut lines = output.lines();
+        let mut items = lines.next().unwrap_or_default().split_ascii_whitespace();
+        if items.next() != Some("systemd") {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Missing systemd version line",
+            ));
+        }
+        let number = items.next().unwrap_or_default();
+        let digits: String = number.chars().take_while(char::is_ascii_digit).collect();
+        let version = digits.parse().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid systemd version \"{number}\""),
+            )
+        })?;
+        let full = items
+            .next()
+            .and_then(|full| full.strip_prefix('('))
+            .and_then(|full| full.strip_suffix(')'))
+            .unwrap_or(number)
+            .to_string();
+        let mut features = BTreeMap::new();
+        let mut settings = BTreeMap::new();
+        for item in lines.flat_map(str::split_ascii_whitespace) {
+            if let Some(feature) = item.strip_prefix('+') {
+                features.insert(feature.to_string(), true);
+            } else if let Some(feature) = item.strip_prefix('-') {
+                features.insert(feature.to_string(), false);
+            } else if let Some((key, value)) = item.split_once('=') {
+                settings.insert(key.to_string(), value.to_string());
+            }
+        }
+        Ok(Self {
+            version,
+            full,
+            features,
+            settings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_version_252() {
+        let version: SystemdVersion = "systemd 252 (252.38-1~deb12u1)
++PAM +AUDIT +SELINUX +APPARMOR +IMA +SMACK +SECCOMP +GCRYPT -GNUTLS +OPENSSL +ACL +BLKID +CURL +ELFUTILS +FIDO2 +IDN2 -IDN +IPTC +KMOD +LIBCRYPTSETUP +LIBFDISK +PCRE2 -PWQUALITY +P11KIT +QRENCODE +TPM2 +BZIP2 +LZ4 +XZ +ZLIB +ZSTD -BPF_FRAMEWORK -XKBCOMMON +UTMP +SYSVINIT default-hierarchy=unified
+"
+        .parse()
+        .unwrap();
+        assert_eq!(version.version, 252);
+        assert_eq!(version.full, "252.38-1~deb12u1");
+        assert!(version.has_feature("PAM"));
+        assert!(!version.has_feature("GNUTLS"));
+        assert!(!version.has_feature("UNKNOWN"));
+        assert_eq!(version.features.get("GNUTLS"), Some(&false));
+        assert_eq!(
+            version
+                .settings
+                .get("default-hierarchy")
+                .map(String::as_str),
+            Some("unified")
+        );
+        assert!(version.supports_json_output());
+        assert!(version.has_vendor_preset_column());
+    }
+
+    #[test]
+    fn test_version_237() {
+        let version: SystemdVersion = "systemd 237
++PAM +AUDIT +SELINUX +IMA +APPARMOR +SMACK +SYSVINIT +UTMP +LIBCRYPTSETUP +GCRYPT +GNUTLS +ACL +XZ +LZ4 +SECCOMP +BLKID +ELFUTILS +KMOD -IDN2 +IDN -PCRE2 default-hierarchy=hybrid
+"
+        .parse()
+        .unwrap();
+        assert_eq!(version.version, 237);
+        assert_eq!(version.full, "237");
+        assert!(!version.has_vendor_preset_column());
+        assert!(!version.supports_freeze());
+        assert!(!version.supports_clean());
+        assert!(!version.supports_json_output());
+    }
+
+    #[test]
+    fn test_invalid_version() {
+        assert!("".parse::<SystemdVersion>().is_err());
+        assert!("systemd rc".parse::<SystemdVersion>().is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{ActiveState, ServiceProperty};
+
+/// Properties a [UnitState] is built from, for `systemctl show --property`
+pub(crate) fn state_properties() -> String {
+    [
+        ServiceProperty::Id,
+        ServiceProperty::ActiveState,
+        ServiceProperty::SubState,
+    ]
+    .map(<&str>::from)
+    .join(",")
+}
+
+/// Active state and sub-state of a unit, like `active (running)`
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnitState {
+    /// High level state
+    pub active: ActiveState,
+    /// Unit type specific state, like `running` or `exited` for services
+    pub sub_state: String,
+}
+
+impl UnitState {
+    /// Builds a `UnitState` from `ActiveState` and `SubState` properties
+    pub(crate) fn from_properties(properties: &BTreeMap<String, String>) -> Self {
+        Self {
+            active: properties
+                .get("ActiveState")
+                .and_then(|state| ActiveState::from_str(state).ok())
+                .unwrap_or(ActiveState::Unknown),
+            sub_state: properties.get("SubState").cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Condition awaited by [crate::SystemCtl::wait_for]:
+/// either a target [ActiveState], or a predicate on the [UnitState]
+pub trait WaitCondition {
+    /// Returns `true` once `state` is the awaited one
+    fn is_met(&self, state: &UnitState) -> bool;
+}
+
+impl WaitCondition for ActiveState {
+    fn is_met(&self, state: &UnitState) -> bool {
+        state.active == *self
+    }
+}
+
+impl<F: Fn(&UnitState) -> bool> WaitCondition for F {
+    fn is_met(&self, state: &UnitState) -> bool {
+        self(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use crate::test::FakeSystemCtl;
+
+    #[test]
+    fn test_conditions() {
+        let state = UnitState {
+            active: ActiveState::Active,
+            sub_state: "running".into(),
+        };
+        assert!(ActiveState::Active.is_met(&state));
+        assert!(!ActiveState::Inactive.is_met(&state));
+        assert!((|state: &UnitState| state.sub_state == "running").is_met(&state));
+
+        let mut properties = BTreeMap::new();
+        properties.insert("ActiveState".to_string(), "activating".to_string());
+        properties.insert("SubState".to_string(), "start-pre".to_string());
+        let state = UnitState::from_properties(&properties);
+        assert_eq!(state.active, ActiveState::Activating);
+        assert_eq!(state.sub_state, "start-pre");
+    }
+
+    #[test]
+    fn test_wait_for() {
+        let mut fake = FakeSystemCtl::new("wait");
+        let counter = fake.file("count");
+        fake.write(&format!(
+            r#"echo >> {counter}
+case "$*" in *show*) ;; *) exit 1 ;; esac
+for unit in "$@"; do
+    case "$unit" in
+        app.service)
+            if [ "$(wc -l < {counter})" -lt 3 ]; then state=activating sub=start; else state=active sub=running; fi
+            printf 'Id=app.service\nActiveState=%s\nSubState=%s\n\n' $state $sub ;;
+        broken.service) printf 'Id=broken.service\nActiveState=failed\nSubState=failed\n\n' ;;
+    esac
+done
+"#,
+            counter = counter.display()
+        ));
+        let ctl = fake.ctl();
+        let poll = Duration::from_millis(10);
+        let state = ctl
+            .wait_for(
+                "app.service",
+                ActiveState::Active,
+                Duration::from_secs(5),
+                poll,
+            )
+            .unwrap();
+        assert_eq!(state.sub_state, "running");
+        assert_eq!(
+            std::fs::read_to_string(&counter).unwrap().lines().count(),
+            3
+        );
+        let e = ctl
+            .wait_for(
+                "broken.service",
+                ActiveState::Active,
+                Duration::from_secs(5),
+                poll,
+            )
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Other);
+        let state = ctl
+            .wait_for(
+                "broken.service",
+                |state: &UnitState| state.active == ActiveState::Failed,
+                Duration::from_secs(5),
+                poll,
+            )
+            .unwrap();
+        assert_eq!(state.active, ActiveState::Failed);
+        let e = ctl
+            .wait_for_all(
+                &["app.service", "broken.service"],
+                ActiveState::Inactive,
+                Duration::from_millis(50),
+                poll,
+            )
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Other);
+        let e = ctl
+            .wait_for(
+                "app.service",
+                ActiveState::Inactive,
+                Duration::from_millis(50),
+                poll,
+            )
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::show::parse_properties;
+use crate::wait::state_properties;
+use crate::{SystemCtl, UnitState};
+
+/// State transition of a unit, reported by a [Watcher]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UnitEvent {
+    /// Unit name, like `ssh.service`
+    pub unit: String,
+    /// Previous state. Units that were not loaded yet are reported as `inactive`.
+    pub old: UnitState,
+    /// New state. Units that got unloaded are reported as `inactive (dead)`.
+    pub new: UnitState,
+    /// Time the transition was detected at
+    pub timestamp: SystemTime,
+}
+
+/// Watches units for state transitions, see [SystemCtl::watch].
+///
+/// Transitions are detected by polling the state of all watched units
+/// with a single `systemctl show` call, so a unit going through several states
+/// within a poll interval only reports the last one.
+/// Use it as a blocking `Iterator`, or as an async `Stream`
+/// with the `async` feature.
+#[derive(Debug)]
+pub struct Watcher {
+    /// systemctl invocation
+    systemctl: SystemCtl,
+    /// Watched unit names or glob patterns
+    units: Vec<String>,
+    /// Time between two polls
+    poll_interval: Duration,
+    /// Last known state of each unit, `None` before the first poll
+    states: Option<BTreeMap<String, UnitState>>,
+    /// Events not yet returned by the iterator
+    events: VecDeque<UnitEvent>,
+}
+
+impl Watcher {
+    /// Creates a watcher of `units`, unit names or glob patterns like `nginx*`
+    pub(crate) fn new(systemctl: SystemCtl, units: &[&str], poll_interval: Duration) -> Self {
+        Self {
+            systemctl,
+            units: units.iter().map(|unit| unit.to_string()).collect(),
+            poll_interval,
+            states: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Returns the current state of the watched units
+    fn current_states(&self) -> std::io::Result<BTreeMap<String, UnitState>> {
+        let property = state_properties();
+        let output = self.systemctl.systemctl_capture_listing(
+            ["show", "--property", property.as_str()]
+                .into_iter()
+                .chain(self.units.iter().map(String::as_str)),
+        )?;
+        Ok(parse_properties(&output)
+            .iter()
+            .filter_map(|properties| {
+                let unit = properties.get("Id")?.clone();
+                Some((unit, UnitState::from_properties(properties)))
+            })
+            .collect())
+    }
+
+    /// Polls the watched units once and returns their transitions
+    /// since the previous poll. The first poll only records the initial states.
+    pub fn poll(&mut self) -> std::io::Result<Vec<UnitEvent>> {
+        let states = self.current_states()?;
+        let Some(previous) = self.states.replace(states.clone()) else {
+            return Ok(Vec::new());
+        };
+        let timestamp = SystemTime::now();
+        let mut events = Vec::new();
+        for (unit, new) in states.iter() {
+            let old = previous.get(unit).cloned().unwrap_or_default();
+            if old != *new {
+                events.push(UnitEvent {
+                    unit: unit.clone(),
+                    old,
+                    new: new.clone(),
+                    timestamp,
+                });
+            }
+        }
+        let unloaded = UnitState {
+            sub_state: "dead".to_string(),
+            ..Default::default()
+        };
+        for (unit, old) in previous {
+            if !states.contains_key(&unit) && old != unloaded {
+                events.push(UnitEvent {
+                    unit,
+                    old,
+                    new: unloaded.clone(),
+                    timestamp,
+                });
+            }
+        }
+        Ok(events)
+    }
+
+    /// Converts this watcher into an async `Stream` of events.
+    /// Polling happens on a background thread, stopped once the stream is dropped.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> stream::WatcherStream {
+        stream::WatcherStream::new(self)
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = std::io::Result<UnitEvent>;
+    /// Blocks until the next transition
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.states.is_some() {
+                std::thread::sleep(self.poll_interval);
+            }
+            match self.poll() {
+                Ok(events) => self.events.extend(events),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+mod stream {
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    use futures_core::Stream;
+
+    use super::{UnitEvent, Watcher};
+
+    /// State shared between a [WatcherStream] and its polling thread
+    #[derive(Default)]
+    struct Shared {
+        /// Events not yet returned by the stream
+        events: VecDeque<std::io::Result<UnitEvent>>,
+        /// Waker of the task awaiting the next event
+        waker: Option<Waker>,
+        /// Set once the stream is dropped
+        closed: bool,
+    }
+
+    /// Async `Stream` of [UnitEvent]s, see [Watcher::into_stream]
+    pub struct WatcherStream {
+        shared: Arc<Mutex<Shared>>,
+    }
+
+    impl WatcherStream {
+        pub(super) fn new(mut watcher: Watcher) -> Self {
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let thread_shared = shared.clone();
+            std::thread::spawn(move || loop {
+                let events = match watcher.poll() {
+                    Ok(events) => events.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                {
+                    let Ok(mut shared) = thread_shared.lock() else {
+                        return;
+                    };
+                    if shared.closed {
+                        return;
+                    }
+                    if !events.is_empty() {
+                        shared.events.extend(events);
+                        if let Some(waker) = shared.waker.take() {
+                            waker.wake();
+                        }
+                    }
+                }
+                std::thread::sleep(watcher.poll_interval);
+            });
+            Self { shared }
+        }
+    }
+
+    impl Stream for WatcherStream {
+        type Item = std::io::Result<UnitEvent>;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            match shared.events.pop_front() {
+                Some(event) => Poll::Ready(Some(event)),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                },
+            }
+        }
+    }
+
+    impl Drop for WatcherStream {
+        fn drop(&mut self) {
+            if let Ok(mut shared) = self.shared.lock() {
+                shared.closed = true;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use stream::WatcherStream;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test::FakeSystemCtl;
+    use crate::ActiveState;
+
+    /// Fake systemctl reporting `app.service` starting up over three polls,
+    /// while `job-1.service`, matched by `job-*`, gets unloaded
+    fn fake_watched_systemctl(name: &str) -> FakeSystemCtl {
+        let mut fake = FakeSystemCtl::new(name);
+        let counter = fake.file("count");
+        fake.write(&format!(
+            r#"echo >> {counter}
+polls=$(wc -l < {counter})
+case "$polls" in
+    1) printf 'Id=app.service\nActiveState=inactive\nSubState=dead\n\nId=job-1.service\nActiveState=active\nSubState=exited\n' ;;
+    2) printf 'Id=app.service\nActiveState=activating\nSubState=start\n\nId=job-1.service\nActiveState=active\nSubState=exited\n' ;;
+    *) printf 'Id=app.service\nActiveState=active\nSubState=running\n' ;;
+esac
+"#,
+            counter = counter.display()
+        ));
+        fake
+    }
+
+    #[test]
+    fn test_watcher() {
+        let fake = fake_watched_systemctl("watch");
+        let mut watcher = fake
+            .ctl()
+            .watch(&["app.service", "job-*"], Duration::from_millis(10));
+        let events: Vec<UnitEvent> = watcher.by_ref().take(3).map(Result::unwrap).collect();
+        // systemctl is gone
+        drop(fake);
+        assert_eq!(events[0].unit, "app.service");
+        assert_eq!(events[0].old.active, ActiveState::Inactive);
+        assert_eq!(events[0].new.active, ActiveState::Activating);
+        assert_eq!(events[1].new.sub_state, "running");
+        assert_eq!(events[1].old.sub_state, "start");
+        assert_eq!(events[2].unit, "job-1.service");
+        assert_eq!(events[2].old.active, ActiveState::Active);
+        assert_eq!(events[2].new.active, ActiveState::Inactive);
+        assert!(events[0].timestamp <= events[1].timestamp);
+        assert!(watcher.poll().is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_watcher_stream() {
+        use futures_core::Stream;
+        let fake = fake_watched_systemctl("watch-stream");
+        let mut stream = fake
+            .ctl()
+            .watch(&["app.service", "job-*"], Duration::from_millis(10))
+            .into_stream();
+        let event = futures_executor::block_on(std::future::poll_fn(|cx| {
+            std::pin::Pin::new(&mut stream).poll_next(cx)
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.new.active, ActiveState::Activating);
+        drop(stream);
+    }
+}